@@ -0,0 +1,251 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use super::{Action, Algorithm};
+use crate::{
+    message::{BroadcastId, Payload},
+    Message, RoutingPlan, Topology,
+};
+
+/// Dolev's reliable broadcast, relaying every copy to all neighbours.
+///
+/// Copies carry the path they travelled, and a node only delivers once it has
+/// received a message over `f + 1` node-disjoint paths, so up to `f` Byzantine
+/// relays cannot make it deliver a forged message.
+pub struct DolevAlgorithm {
+    dolev: Dolev,
+}
+
+/// Dolev's reliable broadcast, relaying copies only over the edges of the route tables.
+pub struct RoutedDolevAlgorithm {
+    dolev: Dolev,
+}
+
+impl Algorithm for DolevAlgorithm {
+    fn new(n: usize, topology: Arc<Topology>, _: Arc<RoutingPlan>) -> Option<Self> {
+        Some(Self {
+            dolev: Dolev::new(n, &topology, None),
+        })
+    }
+
+    fn on_message(&mut self, from: usize, message: Message) -> Vec<Action> {
+        self.dolev.on_message(from, message)
+    }
+
+    fn send_broadcast(&mut self, message: Message) -> Vec<Action> {
+        self.dolev.send_broadcast(message)
+    }
+}
+
+impl Algorithm for RoutedDolevAlgorithm {
+    fn new(n: usize, topology: Arc<Topology>, plan: Arc<RoutingPlan>) -> Option<Self> {
+        let routes = plan.get_table(n)?;
+
+        Some(Self {
+            dolev: Dolev::new(n, &topology, Some(routes)),
+        })
+    }
+
+    fn on_message(&mut self, from: usize, message: Message) -> Vec<Action> {
+        self.dolev.on_message(from, message)
+    }
+
+    fn send_broadcast(&mut self, message: Message) -> Vec<Action> {
+        self.dolev.send_broadcast(message)
+    }
+}
+
+/// State shared by both variants, which only differ in who a copy is relayed to.
+struct Dolev {
+    label: usize,
+    f: usize,
+    neighbours: Vec<usize>,
+    routes: Option<HashMap<usize, HashSet<usize>>>,
+    contents: HashMap<(BroadcastId, Payload), Content>,
+}
+
+#[derive(Default)]
+struct Content {
+    delivered: bool,
+    paths: Vec<Vec<usize>>,
+}
+
+impl Dolev {
+    fn new(
+        label: usize,
+        topology: &Topology,
+        routes: Option<HashMap<usize, HashSet<usize>>>,
+    ) -> Self {
+        Dolev {
+            label,
+            f: topology.get_faulty().len(),
+            neighbours: topology.get_neighbours(label),
+            routes,
+            contents: HashMap::new(),
+        }
+    }
+
+    fn on_message(&mut self, from: usize, message: Message) -> Vec<Action> {
+        let source = message.get_broadcast().get_source();
+
+        // The link is authenticated, so the last hop is known for sure.
+        let mut path = message.get_path().unwrap_or_default();
+        path.push(from);
+
+        if path.contains(&self.label) || source == self.label {
+            return Vec::new();
+        }
+
+        // Only the relays count towards disjointness, not the source itself.
+        let relays = path
+            .iter()
+            .copied()
+            .filter(|&p| p != source)
+            .collect::<Vec<usize>>();
+
+        let content = self
+            .contents
+            .entry((message.get_broadcast(), message.get_payload()))
+            .or_default();
+
+        if content.paths.contains(&relays) {
+            return Vec::new();
+        }
+
+        content.paths.push(relays);
+        let mut actions = Vec::new();
+
+        if !content.delivered && disjoint_paths(&content.paths, self.f + 1) {
+            content.delivered = true;
+            actions.push(Action::Deliver(message.get_content()));
+        }
+
+        let relayed = message.get_content().with_path(path.clone());
+
+        for &to in &self.neighbours {
+            if to == source || path.contains(&to) || !self.routes_to(source, to) {
+                continue;
+            }
+
+            actions.push(Action::Send(to, relayed.clone()));
+        }
+
+        actions
+    }
+
+    fn send_broadcast(&mut self, message: Message) -> Vec<Action> {
+        let content = self
+            .contents
+            .entry((message.get_broadcast(), message.get_payload()))
+            .or_default();
+        content.delivered = true;
+
+        let mut actions = vec![Action::Deliver(message.clone())];
+        let message = message.with_path(Vec::new());
+
+        for &to in &self.neighbours {
+            if !self.routes_to(self.label, to) {
+                continue;
+            }
+
+            actions.push(Action::Send(to, message.clone()));
+        }
+
+        actions
+    }
+
+    fn routes_to(&self, source: usize, to: usize) -> bool {
+        match &self.routes {
+            Some(routes) => routes.get(&source).is_some_and(|r| r.contains(&to)),
+            None => true,
+        }
+    }
+}
+
+/// Whether `k` pairwise node-disjoint paths can be picked from `paths`.
+///
+/// A path without relays came straight from the source, which is enough on its own.
+fn disjoint_paths(paths: &[Vec<usize>], k: usize) -> bool {
+    if paths.iter().any(|p| p.is_empty()) {
+        return true;
+    }
+
+    let mut sorted = paths.iter().collect::<Vec<&Vec<usize>>>();
+    sorted.sort_by_key(|p| p.len());
+
+    pick_disjoint(&sorted, 0, k, &mut HashSet::new())
+}
+
+pub(super) fn pick_disjoint(
+    paths: &[&Vec<usize>],
+    from: usize,
+    k: usize,
+    used: &mut HashSet<usize>,
+) -> bool {
+    if k == 0 {
+        return true;
+    }
+
+    if paths.len() - from < k {
+        return false;
+    }
+
+    for i in from..paths.len() {
+        if paths[i].iter().any(|p| used.contains(p)) {
+            continue;
+        }
+
+        used.extend(paths[i].iter().copied());
+
+        if pick_disjoint(paths, i + 1, k - 1, used) {
+            return true;
+        }
+
+        for p in paths[i] {
+            used.remove(p);
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{byzantine::Forge, strategy::Pathfind, Graph, RouteCache, Seed};
+
+    #[test]
+    fn disjoint_path_selection() {
+        let paths = vec![vec![1, 2], vec![2, 3], vec![4]];
+        assert!(disjoint_paths(&paths, 2));
+        assert!(!disjoint_paths(&paths, 3));
+
+        // The greedy choice of the shortest path would block the other two.
+        let paths = vec![vec![1, 2, 3], vec![4, 5, 6], vec![3, 4]];
+        assert!(disjoint_paths(&paths, 2));
+
+        let paths = vec![vec![], vec![1]];
+        assert!(disjoint_paths(&paths, 5));
+    }
+
+    #[tokio::test]
+    async fn forged_relay_is_not_delivered() {
+        let topology = Arc::new(Topology::complete(5, vec![4]));
+        let cache = Arc::new(RouteCache::new(Arc::new(Pathfind)));
+        let mut graph: Graph<DolevAlgorithm> = Graph::new_simulated(topology, cache, Seed::new(0))
+            .await
+            .unwrap();
+        graph.set_behavior(4, Forge::new(1)).await;
+
+        let sender = graph.get_nodes()[0].clone();
+        graph
+            .broadcast(sender, Message::new(BroadcastId::new(0, 0), "msg"))
+            .await;
+        graph.wait_settled().await;
+
+        assert_eq!(graph.get_delivered_broadcasts().await, 100.);
+        assert_eq!(graph.get_forged_deliveries().await, 0);
+    }
+}
@@ -1,69 +1,102 @@
-use std::{
-    collections::HashSet,
-    sync::{Arc, Mutex},
-};
+use std::{collections::HashSet, sync::Arc};
 
-use async_trait::async_trait;
-use tokio::sync::RwLock;
-
-use super::Algorithm;
-use crate::{node::Node, Message, RouteCache, Topology};
+use super::{Action, Algorithm};
+use crate::{message::BroadcastId, Message, RoutingPlan, Topology};
 
 pub struct FloodingAlgorithm {
-    received: HashSet<String>,
+    neighbours: Vec<usize>,
+    received: HashSet<BroadcastId>,
 }
 
-#[async_trait]
 impl Algorithm for FloodingAlgorithm {
-    fn new(_: usize, _: Arc<Topology>, _: Arc<Mutex<RouteCache>>) -> Option<Self> {
+    fn new(n: usize, topology: Arc<Topology>, _: Arc<RoutingPlan>) -> Option<Self> {
         Some(Self {
+            neighbours: topology.get_neighbours(n),
             received: HashSet::new(),
         })
     }
 
-    async fn on_message<T: 'static>(
-        &mut self,
-        current: Arc<RwLock<Node<T>>>,
-        sender: Arc<RwLock<Node<T>>>,
-        message: Message,
-    ) where
-        T: Algorithm + Send + Sync + 'static,
-    {
-        let id = message.get_id();
+    fn on_message(&mut self, from: usize, message: Message) -> Vec<Action> {
+        let id = message.get_broadcast();
         if self.received.contains(&id) {
-            return;
+            return Vec::new();
         }
 
         self.received.insert(id);
-        current.write().await.deliver(message.clone());
-        let current = current.read().await;
-        let edges = current.get_edges();
-
-        let sender_locked = sender.read().await;
-        let sender_label = sender_locked.get_label();
-        drop(sender_locked);
+        let mut actions = vec![Action::Deliver(message.clone())];
 
-        for edge in edges {
-            let mut edge = (*edge).write().await;
-            if sender_label == edge.to_label().await {
+        for &to in &self.neighbours {
+            if to == from {
                 continue;
             }
 
-            edge.send(message.clone()).await;
+            actions.push(Action::Send(to, message.clone()));
         }
+
+        actions
     }
 
-    async fn send_broadcast<T: 'static>(&mut self, current: Arc<RwLock<Node<T>>>, message: Message)
-    where
-        T: Algorithm + Send + Sync + 'static,
-    {
-        self.received.insert(message.get_id());
-        current.write().await.deliver(message.clone());
-        let node = current.read().await;
-
-        for edge in node.get_edges() {
-            let mut edge = (*edge).write().await;
-            edge.send(message.clone()).await;
+    fn send_broadcast(&mut self, message: Message) -> Vec<Action> {
+        self.received.insert(message.get_broadcast());
+        let mut actions = vec![Action::Deliver(message.clone())];
+
+        for &to in &self.neighbours {
+            actions.push(Action::Send(to, message.clone()));
         }
+
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{strategy::Pathfind, Graph, RouteCache, Seed};
+
+    #[test]
+    fn floods_once_to_all_but_sender() {
+        let topology = Arc::new(Topology::from_edges(vec![(0, 1), (0, 2), (1, 2)], vec![]));
+        let cache = Arc::new(RouteCache::new(Arc::new(Pathfind)));
+        let plan = Arc::new(RoutingPlan::new(topology.clone(), cache));
+        let mut flooding = FloodingAlgorithm::new(0, topology, plan).unwrap();
+        let message = Message::new(BroadcastId::new(1, 0), "msg");
+
+        let actions = flooding.on_message(1, message.clone());
+        assert_eq!(
+            actions,
+            vec![
+                Action::Deliver(message.clone()),
+                Action::Send(2, message.clone())
+            ]
+        );
+
+        assert!(flooding.on_message(2, message).is_empty());
+    }
+
+    #[tokio::test]
+    async fn delivery_times_are_recorded() {
+        let mut topology = Topology::from_edges(vec![(0, 1), (1, 2)], vec![]);
+        topology.set_latency(1, 2, Duration::from_secs(10));
+        let cache = Arc::new(RouteCache::new(Arc::new(Pathfind)));
+        let mut graph: Graph<FloodingAlgorithm> =
+            Graph::new_simulated(Arc::new(topology), cache, Seed::new(0))
+                .await
+                .unwrap();
+
+        let sender = graph.get_nodes()[0].clone();
+        graph
+            .broadcast(sender, Message::new(BroadcastId::new(0, 0), "msg"))
+            .await;
+        graph.wait_settled().await;
+
+        // The source delivers right away, and the far node only after the slow edge.
+        let mut times = graph.get_delivery_times();
+        times.sort();
+        assert_eq!(times.len(), 3);
+        assert_eq!(times[0], Duration::ZERO);
+        assert!(times[2] > Duration::from_secs(1));
+        assert_eq!(Some(times[2]), graph.get_virtual_time());
     }
 }
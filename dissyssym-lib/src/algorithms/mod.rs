@@ -1,33 +1,40 @@
+mod bracha;
+mod dolev;
 mod flooding;
 mod routed;
 
-use std::sync::{Arc, Mutex};
-
-use async_trait::async_trait;
-use tokio::sync::RwLock;
+use std::sync::Arc;
 
+pub use bracha::{
+    BrachaDolevAlgorithm, DolevOptimised, Modifications, Optimised, Unoptimised, Variant,
+};
+pub use dolev::{DolevAlgorithm, RoutedDolevAlgorithm};
 pub use flooding::FloodingAlgorithm;
 pub use routed::RoutedAlgorithm;
 
-use crate::{node::Node, Message, RouteCache, Topology};
+use crate::{Message, RoutingPlan, Topology};
+
+/// What an algorithm wants its node to do after handling an event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Send the message to the neighbour with the given label.
+    Send(usize, Message),
+    /// Deliver the message to the application.
+    Deliver(Message),
+}
 
-#[async_trait]
+/// A broadcast algorithm as a state machine of a single node.
+///
+/// Algorithms never touch the network themselves: the node running them applies
+/// the returned actions, so the same algorithm runs on tokio and the event queue.
 pub trait Algorithm: Sized {
-    fn new(
-        node_id: usize,
-        topology: Arc<Topology>,
-        route_cache: Arc<Mutex<RouteCache>>,
-    ) -> Option<Self>;
+    /// Creates the algorithm of node `node_id`. Routes are read from the `plan`
+    /// shared by all nodes of the graph.
+    fn new(node_id: usize, topology: Arc<Topology>, plan: Arc<RoutingPlan>) -> Option<Self>;
 
-    async fn on_message<T>(
-        &mut self,
-        current: Arc<RwLock<Node<T>>>,
-        sender: Arc<RwLock<Node<T>>>,
-        message: Message,
-    ) where
-        T: Algorithm + 'static + Send + Sync;
+    /// Handles a message received from neighbour `from`.
+    fn on_message(&mut self, from: usize, message: Message) -> Vec<Action>;
 
-    async fn send_broadcast<T>(&mut self, current: Arc<RwLock<Node<T>>>, message: Message)
-    where
-        T: Algorithm + 'static + Send + Sync;
+    /// Starts a broadcast of `message` from this node.
+    fn send_broadcast(&mut self, message: Message) -> Vec<Action>;
 }
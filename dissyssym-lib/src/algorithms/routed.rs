@@ -1,104 +1,57 @@
 use std::{
     collections::{HashMap, HashSet},
-    sync::{Arc, Mutex},
+    sync::Arc,
 };
 
-use async_trait::async_trait;
-use tokio::sync::RwLock;
-
-use super::Algorithm;
-use crate::{node::Node, topology::FlowGraph, Message, RouteCache, Topology};
+use super::{Action, Algorithm};
+use crate::{message::BroadcastId, Message, RoutingPlan, Topology};
 
 pub struct RoutedAlgorithm {
-    received: HashSet<String>,
+    neighbours: Vec<usize>,
+    received: HashSet<BroadcastId>,
     routes: HashMap<usize, HashSet<usize>>,
 }
 
-#[async_trait]
 impl Algorithm for RoutedAlgorithm {
-    fn new(n: usize, topology: Arc<Topology>, route_cache: Arc<Mutex<RouteCache>>) -> Option<Self> {
-        let mut routes = HashMap::new();
-        let nodes = FlowGraph::new(&topology.get_edges()).get_nodes();
-        let f = topology.get_faulty().len();
-
-        for (s, _) in &nodes {
-            let route = match Self::build_routes(route_cache.clone(), &nodes, f, *s, n) {
-                Some(r) => r,
-                None => return None,
-            };
-
-            routes.insert(*s, route);
-        }
+    fn new(n: usize, topology: Arc<Topology>, plan: Arc<RoutingPlan>) -> Option<Self> {
+        let routes = plan.get_table(n)?;
 
         Some(Self {
+            neighbours: topology.get_neighbours(n),
             routes,
             received: HashSet::new(),
         })
     }
 
-    async fn on_message<T>(
-        &mut self,
-        current: Arc<RwLock<Node<T>>>,
-        _sender: Arc<RwLock<Node<T>>>,
-        message: Message,
-    ) where
-        T: Algorithm + Send + Sync + 'static,
-    {
-        let id = message.get_id();
+    fn on_message(&mut self, _from: usize, message: Message) -> Vec<Action> {
+        let id = message.get_broadcast();
         if self.received.contains(&id) {
-            return;
+            return Vec::new();
         }
 
         self.received.insert(id);
-        current.write().await.deliver(message.clone());
-        let current = current.read().await;
-        let edges = current.get_edges();
-
-        for edge in edges {
-            let mut edge = edge.write().await;
-            let label = edge.to_label().await;
+        let mut actions = vec![Action::Deliver(message.clone())];
+        let route = self.routes.get(&id.get_source()).unwrap();
 
-            if !self
-                .routes
-                .get(&message.get_sender())
-                .unwrap()
-                .contains(&label)
-            {
+        for &to in &self.neighbours {
+            if !route.contains(&to) {
                 continue;
             }
 
-            edge.send(message.clone()).await;
+            actions.push(Action::Send(to, message.clone()));
         }
-    }
-
-    async fn send_broadcast<T: 'static>(&mut self, current: Arc<RwLock<Node<T>>>, message: Message)
-    where
-        T: Algorithm + Send + Sync + 'static,
-    {
-        self.received.insert(message.get_id());
-        current.write().await.deliver(message.clone());
-        let node = current.read().await;
 
-        for edge in node.get_edges() {
-            let mut edge = (*edge).write().await;
-            edge.send(message.clone()).await;
-        }
+        actions
     }
-}
 
-impl RoutedAlgorithm {
-    fn build_routes(
-        cache: Arc<Mutex<RouteCache>>,
-        nodes: &HashMap<usize, HashSet<usize>>,
-        f: usize,
-        s: usize,
-        n: usize,
-    ) -> Option<HashSet<usize>> {
-        let mut lock = cache.lock().unwrap();
+    fn send_broadcast(&mut self, message: Message) -> Vec<Action> {
+        self.received.insert(message.get_broadcast());
+        let mut actions = vec![Action::Deliver(message.clone())];
 
-        match lock.gen_routes(nodes, f, s) {
-            Some(r) => r.get(&n).map(|r| r.clone()),
-            None => return None,
+        for &to in &self.neighbours {
+            actions.push(Action::Send(to, message.clone()));
         }
+
+        actions
     }
 }
@@ -0,0 +1,206 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{message::BroadcastId, Message, MessageKind};
+
+pub type SharedBehavior = Arc<Mutex<Box<dyn ByzantineBehavior>>>;
+
+/// What a faulty node does instead of following its algorithm.
+///
+/// The algorithm of a faulty node still runs, but the behaviour decides which
+/// incoming messages it gets to see and rewrites everything it sends.
+pub trait ByzantineBehavior: Debug + Send {
+    /// Called for every message reaching the faulty node, returns whether the
+    /// algorithm of the node gets to handle it.
+    fn on_receive(&mut self, _from: usize, _msg: &Message) -> bool {
+        true
+    }
+
+    /// Called for every message the algorithm sends to neighbour `to`, returns
+    /// what is actually put on the edge.
+    fn on_send(&mut self, to: usize, msg: Message) -> Vec<Outgoing>;
+}
+
+/// A message put on an edge by a faulty node, with extra delay on top of the link delay.
+#[derive(Debug, Clone)]
+pub struct Outgoing {
+    pub msg: Message,
+    pub delay: Duration,
+}
+
+impl Outgoing {
+    pub fn now(msg: Message) -> Self {
+        Outgoing {
+            msg,
+            delay: Duration::ZERO,
+        }
+    }
+}
+
+/// Crashes: ignores every message it receives, so it never relays anything.
+/// Broadcasts started by the node itself still go out.
+#[derive(Debug, Default)]
+pub struct Silent;
+
+impl ByzantineBehavior for Silent {
+    fn on_receive(&mut self, _: usize, _: &Message) -> bool {
+        false
+    }
+
+    fn on_send(&mut self, _: usize, msg: Message) -> Vec<Outgoing> {
+        vec![Outgoing::now(msg)]
+    }
+}
+
+/// Follows the algorithm, but never sends anything to the given neighbours.
+#[derive(Debug)]
+pub struct SelectiveDrop {
+    dropped: HashSet<usize>,
+}
+
+impl SelectiveDrop {
+    pub fn new(dropped: impl IntoIterator<Item = usize>) -> Self {
+        SelectiveDrop {
+            dropped: dropped.into_iter().collect(),
+        }
+    }
+}
+
+impl ByzantineBehavior for SelectiveDrop {
+    fn on_send(&mut self, to: usize, msg: Message) -> Vec<Outgoing> {
+        if self.dropped.contains(&to) {
+            return Vec::new();
+        }
+
+        vec![Outgoing::now(msg)]
+    }
+}
+
+/// Relays everything as if it was broadcast by `claimed` instead.
+#[derive(Debug)]
+pub struct Forge {
+    claimed: usize,
+}
+
+impl Forge {
+    pub fn new(claimed: usize) -> Self {
+        Forge { claimed }
+    }
+}
+
+impl ByzantineBehavior for Forge {
+    fn on_send(&mut self, _: usize, msg: Message) -> Vec<Outgoing> {
+        let mut forged = msg.with_sender(self.claimed);
+
+        if forged.get_kind() == MessageKind::Send {
+            let seq = forged.get_broadcast().get_seq();
+            forged = forged.with_broadcast(BroadcastId::new(self.claimed, seq));
+        }
+
+        vec![Outgoing::now(forged)]
+    }
+}
+
+/// Remembers everything it receives, and sends every old message again to each
+/// neighbour the next time it talks to it.
+#[derive(Debug, Default)]
+pub struct Replay {
+    seen: Vec<Message>,
+    replayed: HashMap<usize, usize>,
+}
+
+impl ByzantineBehavior for Replay {
+    fn on_receive(&mut self, _: usize, msg: &Message) -> bool {
+        self.seen.push(msg.clone());
+        true
+    }
+
+    fn on_send(&mut self, to: usize, msg: Message) -> Vec<Outgoing> {
+        let replayed = self.replayed.entry(to).or_insert(0);
+        let mut out = vec![Outgoing::now(msg)];
+
+        for old in &self.seen[*replayed..] {
+            out.push(Outgoing::now(old.clone()));
+        }
+
+        *replayed = self.seen.len();
+        out
+    }
+}
+
+/// Tells every neighbour a different story, by tagging the payload with the receiver.
+#[derive(Debug, Default)]
+pub struct Equivocate;
+
+impl ByzantineBehavior for Equivocate {
+    fn on_send(&mut self, to: usize, msg: Message) -> Vec<Outgoing> {
+        let mut payload = msg.get_payload().as_bytes().to_vec();
+        payload.extend(format!("/{}", to).bytes());
+        let forged = msg.with_payload(payload);
+
+        vec![Outgoing::now(forged)]
+    }
+}
+
+/// Follows the algorithm, but holds every message back for an extra `delay`.
+#[derive(Debug)]
+pub struct Delay {
+    delay: Duration,
+}
+
+impl Delay {
+    pub fn new(delay: Duration) -> Self {
+        Delay { delay }
+    }
+}
+
+impl ByzantineBehavior for Delay {
+    fn on_send(&mut self, _: usize, msg: Message) -> Vec<Outgoing> {
+        vec![Outgoing {
+            msg,
+            delay: self.delay,
+        }]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay_sends_old_messages_once_per_neighbour() {
+        let mut replay = Replay::default();
+        let id = BroadcastId::new(0, 0);
+        replay.on_receive(0, &Message::new(id, "a"));
+        replay.on_receive(0, &Message::new(id, "b"));
+
+        let out = replay.on_send(1, Message::new(id, "c"));
+        let payloads = out
+            .iter()
+            .map(|o| o.msg.get_payload().as_bytes().to_vec())
+            .collect::<Vec<Vec<u8>>>();
+        assert_eq!(payloads, vec![b"c".to_vec(), b"a".to_vec(), b"b".to_vec()]);
+
+        let out = replay.on_send(1, Message::new(id, "d"));
+        assert_eq!(out.len(), 1);
+
+        let out = replay.on_send(2, Message::new(id, "d"));
+        assert_eq!(out.len(), 3);
+    }
+
+    #[test]
+    fn equivocate_differs_per_neighbour() {
+        let mut equivocate = Equivocate;
+        let id = BroadcastId::new(0, 0);
+        let a = equivocate.on_send(1, Message::new(id, "m"));
+        let b = equivocate.on_send(2, Message::new(id, "m"));
+
+        assert_ne!(a[0].msg.get_payload(), b[0].msg.get_payload());
+        assert_eq!(a[0].msg.get_broadcast(), b[0].msg.get_broadcast());
+        assert_eq!(a[0].msg.get_sender(), 0);
+    }
+}
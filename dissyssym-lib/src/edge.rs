@@ -1,18 +1,23 @@
 use std::{
     sync::{
         atomic::{AtomicU64, Ordering},
-        Arc,
+        Arc, Mutex,
     },
     time::Duration,
 };
 
-use rand_distr::{Distribution, Normal};
 use tokio::{
     sync::{mpsc, RwLock},
     time::sleep,
 };
 
-use crate::{algorithms::Algorithm, message::Message, node::Node};
+use crate::{
+    algorithms::Algorithm,
+    byzantine::{Outgoing, SharedBehavior},
+    engine::{sample_delay, EventQueue},
+    message::Message,
+    node::Node,
+};
 
 #[derive(Debug)]
 pub struct Edge<T>
@@ -21,40 +26,74 @@ where
 {
     id: usize,
     total_messages: u64,
+    total_bytes: u64,
+    received: Arc<AtomicU64>,
+    from: Arc<RwLock<Node<T>>>,
     to: Arc<RwLock<Node<T>>>,
+    latency: Duration,
     unresolved: Arc<AtomicU64>,
-    tx: mpsc::UnboundedSender<Message>,
+    transport: Transport<T>,
+    behavior: Option<SharedBehavior>,
+}
+
+/// How a message travels over an edge: after a real sleep in a spawned tokio
+/// task, or as an event on the virtual-time queue.
+enum Transport<T>
+where
+    T: Algorithm + Send + Sync + 'static,
+{
+    Tokio(mpsc::UnboundedSender<(Message, Duration)>),
+    Simulated(Arc<Mutex<EventQueue<T>>>),
+}
+
+impl<T> std::fmt::Debug for Transport<T>
+where
+    T: Algorithm + Send + Sync + 'static,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Transport::Tokio(_) => write!(f, "Tokio"),
+            Transport::Simulated(_) => write!(f, "Simulated"),
+        }
+    }
 }
 
 impl<T> Edge<T>
 where
     T: Algorithm + Send + Sync + 'static,
 {
+    /// Creates an edge whose messages sleep for a delay sampled around `latency`,
+    /// which is not drawn from the experiment seed.
     pub fn new(
         id: usize,
         from: Arc<RwLock<Node<T>>>,
         to: Arc<RwLock<Node<T>>>,
+        latency: Duration,
         unresolved: Arc<AtomicU64>,
     ) -> Self {
-        let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
+        let (tx, mut rx) = mpsc::unbounded_channel::<(Message, Duration)>();
+        let mov_from = from.clone();
         let mov_to = to.clone();
         let mov_unresolved = unresolved.clone();
+        let received = Arc::new(AtomicU64::new(0));
+        let mov_received = received.clone();
 
         tokio::spawn(async move {
             loop {
-                let msg = match rx.recv().await {
+                let (msg, extra) = match rx.recv().await {
                     Some(it) => it,
-                    _ => continue,
+                    _ => break,
                 };
 
                 let mov_to = mov_to.clone();
-                let from = from.clone();
+                let from = mov_from.clone();
                 let mov_unresolved = mov_unresolved.clone();
+                let mov_received = mov_received.clone();
 
                 tokio::spawn(async move {
-                    let normal = Normal::new(75., 25.).expect("failed to create delay sample.");
-                    let delay: f64 = normal.sample(&mut rand::thread_rng());
-                    sleep(Duration::from_millis(delay.round() as u64)).await;
+                    let delay = sample_delay(latency, &mut rand::thread_rng());
+                    sleep(delay + extra).await;
+                    mov_received.fetch_add(1, Ordering::Relaxed);
                     Node::recv(mov_to, from, msg).await;
                     mov_unresolved.fetch_sub(1, Ordering::AcqRel);
                 });
@@ -63,21 +102,80 @@ where
 
         Self {
             id,
+            from,
+            to,
+            latency,
+            unresolved,
+            transport: Transport::Tokio(tx),
+            behavior: None,
+            total_messages: 0,
+            total_bytes: 0,
+            received,
+        }
+    }
+
+    pub fn new_simulated(
+        id: usize,
+        from: Arc<RwLock<Node<T>>>,
+        to: Arc<RwLock<Node<T>>>,
+        latency: Duration,
+        unresolved: Arc<AtomicU64>,
+        queue: Arc<Mutex<EventQueue<T>>>,
+    ) -> Self {
+        Self {
+            id,
+            from,
             to,
-            tx,
+            latency,
             unresolved,
+            transport: Transport::Simulated(queue),
+            behavior: None,
             total_messages: 0,
+            total_bytes: 0,
+            received: Arc::new(AtomicU64::new(0)),
         }
     }
 
     pub async fn send(&mut self, msg: Message) {
+        let outgoing = match &self.behavior {
+            Some(behavior) => {
+                let to = self.to_label().await;
+                behavior.lock().unwrap().on_send(to, msg)
+            }
+            None => vec![Outgoing::now(msg)],
+        };
+
+        for out in outgoing {
+            self.dispatch(out);
+        }
+    }
+
+    fn dispatch(&mut self, out: Outgoing) {
         self.unresolved.fetch_add(1, Ordering::AcqRel);
-        self.tx
-            .send(msg)
-            .expect("Failed to send message in the channel.");
+        self.total_bytes += out.msg.size() as u64;
+
+        match &self.transport {
+            Transport::Tokio(tx) => tx
+                .send((out.msg, out.delay))
+                .expect("Failed to send message in the channel."),
+            Transport::Simulated(queue) => queue.lock().unwrap().schedule(
+                self.from.clone(),
+                self.to.clone(),
+                out.msg,
+                self.latency,
+                out.delay,
+                self.received.clone(),
+            ),
+        }
+
         self.total_messages += 1;
     }
 
+    /// Lets `behavior` decide what goes over this edge, as its sender is faulty.
+    pub fn set_behavior(&mut self, behavior: SharedBehavior) {
+        self.behavior = Some(behavior);
+    }
+
     pub async fn to_label(&self) -> usize {
         let node = self.to.read().await;
         node.get_label()
@@ -86,6 +184,15 @@ where
     pub fn get_messages(&self) -> u64 {
         self.total_messages
     }
+
+    pub fn get_bytes(&self) -> u64 {
+        self.total_bytes
+    }
+
+    /// The messages sent over this edge that have arrived at its far end.
+    pub fn get_received(&self) -> u64 {
+        self.received.load(Ordering::Relaxed)
+    }
 }
 
 impl<T> PartialEq for Edge<T>
@@ -0,0 +1,131 @@
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    sync::{atomic::AtomicU64, Arc},
+    time::Duration,
+};
+
+use rand::{rngs::StdRng, Rng};
+use rand_distr::StandardNormal;
+use tokio::sync::RwLock;
+
+use crate::{algorithms::Algorithm, message::Message, node::Node, Seed};
+
+/// Single-threaded, virtual-time event queue.
+///
+/// Instead of sleeping for the sampled delay, every message is scheduled at
+/// `now + delay` and handed out in timestamp order, so a whole broadcast
+/// settles without waiting on the wall-clock and runs are reproducible.
+pub struct EventQueue<T>
+where
+    T: Algorithm + Send + Sync + 'static,
+{
+    now: Duration,
+    next_seq: u64,
+    rng: StdRng,
+    events: BinaryHeap<Event<T>>,
+}
+
+impl<T> EventQueue<T>
+where
+    T: Algorithm + Send + Sync + 'static,
+{
+    pub fn new(seed: Seed) -> Self {
+        Self {
+            now: Duration::ZERO,
+            next_seq: 0,
+            rng: seed.rng("delays"),
+            events: BinaryHeap::new(),
+        }
+    }
+
+    /// Schedules `msg` after a link delay sampled around `latency`, plus `extra` on top of it.
+    /// `arrived` is counted up once the message is handed out.
+    pub fn schedule(
+        &mut self,
+        from: Arc<RwLock<Node<T>>>,
+        to: Arc<RwLock<Node<T>>>,
+        msg: Message,
+        latency: Duration,
+        extra: Duration,
+        arrived: Arc<AtomicU64>,
+    ) {
+        let at = self.now + sample_delay(latency, &mut self.rng) + extra;
+
+        self.events.push(Event {
+            at,
+            seq: self.next_seq,
+            from,
+            to,
+            msg,
+            arrived,
+        });
+        self.next_seq += 1;
+    }
+
+    /// Pops the earliest event and advances the virtual clock to it.
+    pub fn pop(&mut self) -> Option<Event<T>> {
+        let event = self.events.pop()?;
+        self.now = event.at;
+
+        Some(event)
+    }
+
+    pub fn get_now(&self) -> Duration {
+        self.now
+    }
+}
+
+/// Draws a link delay from a normal distribution around the expected `latency`,
+/// with a third of it as standard deviation, rounded to whole milliseconds.
+pub fn sample_delay(latency: Duration, rng: &mut impl Rng) -> Duration {
+    let mean = latency.as_secs_f64() * 1000.;
+    let z: f64 = rng.sample(StandardNormal);
+    let delay = mean + mean / 3. * z;
+
+    Duration::from_millis(delay.round() as u64)
+}
+
+pub struct Event<T>
+where
+    T: Algorithm + Send + Sync + 'static,
+{
+    at: Duration,
+    seq: u64,
+    pub from: Arc<RwLock<Node<T>>>,
+    pub to: Arc<RwLock<Node<T>>>,
+    pub msg: Message,
+    /// The arrival counter of the edge the message travels over.
+    pub arrived: Arc<AtomicU64>,
+}
+
+// Ordered in reverse on `(at, seq)`, so the max-heap yields the earliest event
+// first and simultaneous events keep their scheduling order.
+impl<T> Ord for Event<T>
+where
+    T: Algorithm + Send + Sync + 'static,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.at, other.seq).cmp(&(self.at, self.seq))
+    }
+}
+
+impl<T> PartialOrd for Event<T>
+where
+    T: Algorithm + Send + Sync + 'static,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> PartialEq for Event<T>
+where
+    T: Algorithm + Send + Sync + 'static,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.at == other.at && self.seq == other.seq
+    }
+}
+
+impl<T> Eq for Event<T> where T: Algorithm + Send + Sync + 'static {}
@@ -0,0 +1,111 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use sha2::{Digest, Sha512};
+
+/// Identifies the routes of one source in one graph tolerating `f` faulty nodes.
+///
+/// It is hashed from the adjacency structure itself, with nodes and neighbours in
+/// ascending order, so the same graph always gets the same fingerprint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Fingerprint([u8; 16]);
+
+impl Fingerprint {
+    pub fn new(nodes: &HashMap<usize, HashSet<usize>>, f: usize, s: usize) -> Self {
+        GraphFingerprint::new(nodes, f).with_source(s)
+    }
+
+    pub(crate) fn get_byte(&self, i: usize) -> u8 {
+        self.0[i]
+    }
+}
+
+impl Display for Fingerprint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for byte in self.0 {
+            write!(f, "{:02X}", byte)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The part of a fingerprint shared by every source of a graph, so a graph is
+/// hashed once however many sources are looked up.
+#[derive(Clone)]
+pub(crate) struct GraphFingerprint(Sha512);
+
+impl GraphFingerprint {
+    pub(crate) fn new(nodes: &HashMap<usize, HashSet<usize>>, f: usize) -> Self {
+        let mut hasher = Sha512::new();
+        let mut labels = nodes.keys().copied().collect::<Vec<usize>>();
+        labels.sort_unstable();
+
+        hasher.update((labels.len() as u64).to_le_bytes());
+        for n in labels {
+            let mut neighbours = nodes[&n].iter().copied().collect::<Vec<usize>>();
+            neighbours.sort_unstable();
+
+            hasher.update((n as u64).to_le_bytes());
+            hasher.update((neighbours.len() as u64).to_le_bytes());
+            for m in neighbours {
+                hasher.update((m as u64).to_le_bytes());
+            }
+        }
+        hasher.update((f as u64).to_le_bytes());
+
+        GraphFingerprint(hasher)
+    }
+
+    pub(crate) fn with_source(&self, s: usize) -> Fingerprint {
+        self.with_load(s, &HashMap::new())
+    }
+
+    /// Also tells apart the relay load the routes of `s` were built on, where a
+    /// load of zero everywhere is the same as none.
+    pub(crate) fn with_load(&self, s: usize, load: &HashMap<usize, usize>) -> Fingerprint {
+        let mut hasher = self.0.clone();
+        hasher.update((s as u64).to_le_bytes());
+
+        let mut loaded = load
+            .iter()
+            .filter(|&(_, &l)| l > 0)
+            .map(|(&n, &l)| (n, l))
+            .collect::<Vec<(usize, usize)>>();
+        loaded.sort_unstable();
+        for (n, l) in loaded {
+            hasher.update((n as u64).to_le_bytes());
+            hasher.update((l as u64).to_le_bytes());
+        }
+
+        let mut bytes = [0; 16];
+        bytes.copy_from_slice(&hasher.finalize()[..16]);
+
+        Fingerprint(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FlowGraph;
+
+    #[test]
+    fn fingerprints_are_canonical() {
+        let nodes = FlowGraph::new(&vec![(0, 1), (1, 2), (2, 3), (0, 3)]).get_nodes();
+        let reversed = FlowGraph::new(&vec![(3, 0), (3, 2), (2, 1), (1, 0)]).get_nodes();
+        let fingerprint = Fingerprint::new(&nodes, 1, 0);
+
+        assert_eq!(fingerprint, Fingerprint::new(&reversed, 1, 0));
+        assert_eq!(fingerprint.to_string().len(), 32);
+
+        assert_ne!(fingerprint, Fingerprint::new(&nodes, 2, 0));
+        assert_ne!(fingerprint, Fingerprint::new(&nodes, 1, 1));
+
+        // Moving an edge keeps every degree, but not the fingerprint.
+        let moved = FlowGraph::new(&vec![(0, 2), (1, 2), (1, 3), (0, 3)]).get_nodes();
+        assert_ne!(fingerprint, Fingerprint::new(&moved, 1, 0));
+    }
+}
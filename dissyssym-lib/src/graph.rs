@@ -6,10 +6,17 @@ use std::{
     time::Duration,
 };
 
-use tokio::{sync::RwLock, time::sleep};
+use tokio::{sync::RwLock, task, time::sleep};
 
 use crate::{
-    algorithms::Algorithm, edge::Edge, message::Message, node::Node, RouteCache, Topology,
+    algorithms::Algorithm,
+    byzantine::{ByzantineBehavior, Silent},
+    edge::Edge,
+    engine::{Event, EventQueue},
+    load::Load,
+    message::Message,
+    node::Node,
+    RouteCache, RoutingPlan, Seed, Topology,
 };
 
 pub struct Graph<T>
@@ -20,51 +27,101 @@ where
     unresolved: Arc<AtomicU64>,
     send_messages: Vec<Message>,
     topology: Arc<Topology>,
+    queue: Option<Arc<Mutex<EventQueue<T>>>>,
+    deliveries: Mutex<Vec<(usize, Message, Duration)>>,
 }
 
 impl<T> Graph<T>
 where
     T: Algorithm + Send + Sync + 'static,
 {
-    pub async fn new(topology: Arc<Topology>, route_cache: Arc<Mutex<RouteCache>>) -> Option<Self> {
+    /// Creates a graph whose messages are sent by tokio tasks after real sleeps.
+    ///
+    /// Message delays are drawn from the thread's random generator and arrive in
+    /// whatever order the scheduler picks, so runs on such a graph cannot be
+    /// reproduced from a seed. Use [`Graph::new_simulated`] for that.
+    pub async fn new(topology: Arc<Topology>, route_cache: Arc<RouteCache>) -> Option<Self> {
+        Self::build(topology, route_cache, None).await
+    }
+
+    /// Creates a graph driven by the deterministic event queue instead of tokio sleeps.
+    /// Message delays are drawn from the `delays` stream of `seed`.
+    pub async fn new_simulated(
+        topology: Arc<Topology>,
+        route_cache: Arc<RouteCache>,
+        seed: Seed,
+    ) -> Option<Self> {
+        let queue = Arc::new(Mutex::new(EventQueue::new(seed)));
+
+        Self::build(topology, route_cache, Some(queue)).await
+    }
+
+    async fn build(
+        topology: Arc<Topology>,
+        route_cache: Arc<RouteCache>,
+        queue: Option<Arc<Mutex<EventQueue<T>>>>,
+    ) -> Option<Self> {
         let mut next_edge = 0;
-        let mut nodes = Vec::with_capacity(topology.get_n());
         let unresolved = Arc::new(AtomicU64::new(0));
+        let plan = Arc::new(RoutingPlan::new(topology.clone(), route_cache));
 
-        for n in 0..topology.get_n() {
-            let node = match Node::new(n, topology.clone(), route_cache.clone()) {
-                Some(n) => n,
-                None => return None,
-            };
-            nodes.push(node);
-        }
+        // Algorithms may generate the routes of the graph, or wait for another graph
+        // generating the same ones, which must not hold up a tokio worker.
+        let nodes = {
+            let topology = topology.clone();
+
+            task::spawn_blocking(move || {
+                (0..topology.get_n())
+                    .map(|n| Node::new(n, topology.clone(), plan.clone()))
+                    .collect::<Option<Vec<_>>>()
+            })
+            .await
+            .expect("Failed to create the nodes.")?
+        };
 
         for (a, b) in topology.get_edges() {
             next_edge = Self::connect_nodes(
                 nodes[a].clone(),
                 nodes[b].clone(),
+                topology.get_latency(a, b),
                 next_edge,
                 unresolved.clone(),
+                queue.clone(),
             )
             .await;
         }
 
         for n in topology.get_faulty() {
-            nodes[n].write().await.set_faulty();
+            nodes[n].write().await.set_faulty(Box::new(Silent)).await;
         }
 
         Some(Self {
             nodes,
             unresolved,
             topology,
+            queue,
             send_messages: Vec::new(),
+            deliveries: Mutex::new(Vec::new()),
         })
     }
 
     pub async fn broadcast(&mut self, node: Arc<RwLock<Node<T>>>, msg: Message) {
         self.send_messages.push(msg.clone());
 
-        Node::broadcast(node, msg).await
+        let delivered = node.read().await.get_delivered_count();
+        Node::broadcast(node.clone(), msg).await;
+        self.record_deliveries(&node, delivered).await;
+    }
+
+    /// Replaces the behaviour of a faulty node, which is silent by default.
+    pub async fn set_behavior(&self, label: usize, behavior: impl ByzantineBehavior + 'static) {
+        let mut node = self.nodes[label].write().await;
+        assert!(
+            node.get_faulty(),
+            "Only faulty nodes can be given a Byzantine behaviour."
+        );
+
+        node.set_faulty(Box::new(behavior)).await;
     }
 
     pub fn get_nodes(&self) -> Vec<Arc<RwLock<Node<T>>>> {
@@ -72,11 +129,65 @@ where
     }
 
     pub async fn wait_settled(&self) {
+        if let Some(queue) = &self.queue {
+            while let Some(event) = Self::next_event(queue) {
+                let delivered = event.to.read().await.get_delivered_count();
+                event.arrived.fetch_add(1, Ordering::Relaxed);
+                Node::recv(event.to.clone(), event.from, event.msg).await;
+                self.record_deliveries(&event.to, delivered).await;
+                self.unresolved.fetch_sub(1, Ordering::AcqRel);
+            }
+
+            return;
+        }
+
         while self.unresolved.load(Ordering::Acquire) != 0 {
             sleep(Duration::from_millis(42)).await
         }
     }
 
+    /// The virtual time at which the last message arrived, if this graph is simulated.
+    pub fn get_virtual_time(&self) -> Option<Duration> {
+        self.queue
+            .as_ref()
+            .map(|queue| queue.lock().unwrap().get_now())
+    }
+
+    /// Notes the virtual time of what `node` delivered beyond its first `before`
+    /// deliveries, if this graph is simulated.
+    async fn record_deliveries(&self, node: &RwLock<Node<T>>, before: usize) {
+        let now = match self.get_virtual_time() {
+            Some(now) => now,
+            None => return,
+        };
+
+        let node = node.read().await;
+        let mut deliveries = self.deliveries.lock().unwrap();
+
+        for msg in node.get_delivered_since(before) {
+            deliveries.push((node.get_label(), msg.clone(), now));
+        }
+    }
+
+    /// The virtual times at which correct nodes delivered a broadcast, one for
+    /// every delivery. Only recorded if this graph is simulated.
+    pub fn get_delivery_times(&self) -> Vec<Duration> {
+        let faulty = self.topology.get_faulty();
+
+        self.deliveries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(label, msg, _)| !faulty.contains(label) && self.send_messages.contains(msg))
+            .map(|&(_, _, at)| at)
+            .collect()
+    }
+
+    // Split out so the queue lock is released before the event is handled.
+    fn next_event(queue: &Mutex<EventQueue<T>>) -> Option<Event<T>> {
+        queue.lock().unwrap().pop()
+    }
+
     pub async fn print(&self) {
         for node in &self.nodes {
             let node = node.read().await;
@@ -99,6 +210,42 @@ where
         total
     }
 
+    /// The messages sent and received by every node and over every edge.
+    pub async fn get_load(&self) -> Load {
+        let mut sent = Vec::with_capacity(self.nodes.len());
+        let mut received = Vec::with_capacity(self.nodes.len());
+        let mut edges = Vec::new();
+
+        for node in &self.nodes {
+            let node = node.read().await;
+            sent.push(node.get_messages().await);
+            received.push(node.get_received());
+
+            for edge in node.get_edges() {
+                let edge = edge.read().await;
+                edges.push((
+                    node.get_label(),
+                    edge.to_label().await,
+                    edge.get_messages(),
+                    edge.get_received(),
+                ));
+            }
+        }
+
+        Load::new(sent, received, edges)
+    }
+
+    /// The bytes sent over all edges, counting the headers of every message.
+    pub async fn get_total_bytes(&self) -> u64 {
+        let mut total = 0;
+
+        for node in &self.nodes {
+            total += node.read().await.get_bytes().await;
+        }
+
+        total
+    }
+
     pub async fn get_delivered_broadcasts(&self) -> f64 {
         let mut total = 0;
 
@@ -112,7 +259,11 @@ where
                 continue;
             }
 
-            total += node.get_delivered().len();
+            total += node
+                .get_delivered()
+                .iter()
+                .filter(|msg| self.send_messages.contains(msg))
+                .count();
         }
 
         let expected = self.send_messages.len() * (n - f);
@@ -120,28 +271,124 @@ where
         (total as f64 / expected as f64) * 100.
     }
 
+    /// Counts the messages delivered by correct nodes that were never broadcast,
+    /// i.e. the ones made up by faulty nodes.
+    pub async fn get_forged_deliveries(&self) -> usize {
+        let mut total = 0;
+
+        for node in &self.nodes {
+            let node = node.read().await;
+
+            if node.get_faulty() {
+                continue;
+            }
+
+            total += node
+                .get_delivered()
+                .iter()
+                .filter(|msg| !self.send_messages.contains(msg))
+                .count();
+        }
+
+        total
+    }
+
     async fn connect_nodes(
         al: Arc<RwLock<Node<T>>>,
         bl: Arc<RwLock<Node<T>>>,
+        latency: Duration,
         next_edge: usize,
         unresolved: Arc<AtomicU64>,
+        queue: Option<Arc<Mutex<EventQueue<T>>>>,
     ) -> usize {
         let mut node = al.write().await;
         let mut new_node = bl.write().await;
+        let (a, b) = (node.get_label(), new_node.get_label());
 
-        node.add_edge(Edge::new(
-            next_edge,
-            al.clone(),
-            bl.clone(),
-            unresolved.clone(),
-        ));
-        new_node.add_edge(Edge::new(
-            next_edge + 1,
-            bl.clone(),
-            al.clone(),
-            unresolved.clone(),
-        ));
+        match queue {
+            Some(queue) => {
+                node.add_edge(
+                    b,
+                    Edge::new_simulated(
+                        next_edge,
+                        al.clone(),
+                        bl.clone(),
+                        latency,
+                        unresolved.clone(),
+                        queue.clone(),
+                    ),
+                );
+                new_node.add_edge(
+                    a,
+                    Edge::new_simulated(
+                        next_edge + 1,
+                        bl.clone(),
+                        al.clone(),
+                        latency,
+                        unresolved.clone(),
+                        queue,
+                    ),
+                );
+            }
+            None => {
+                node.add_edge(
+                    b,
+                    Edge::new(
+                        next_edge,
+                        al.clone(),
+                        bl.clone(),
+                        latency,
+                        unresolved.clone(),
+                    ),
+                );
+                new_node.add_edge(
+                    a,
+                    Edge::new(
+                        next_edge + 1,
+                        bl.clone(),
+                        al.clone(),
+                        latency,
+                        unresolved.clone(),
+                    ),
+                );
+            }
+        }
 
         next_edge + 2
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{algorithms::FloodingAlgorithm, strategy::Pathfind, BroadcastId};
+
+    #[tokio::test]
+    async fn load_is_counted_per_node_and_edge() {
+        // A star around node 0, where a broadcast from leaf 1 goes through the hub.
+        let topology = Topology::from_edges(vec![(0, 1), (0, 2), (0, 3)], vec![]);
+        let cache = Arc::new(RouteCache::new(Arc::new(Pathfind)));
+        let mut graph: Graph<FloodingAlgorithm> =
+            Graph::new_simulated(Arc::new(topology), cache, Seed::new(0))
+                .await
+                .unwrap();
+
+        let sender = graph.get_nodes()[1].clone();
+        graph
+            .broadcast(sender, Message::new(BroadcastId::new(1, 0), "msg"))
+            .await;
+        graph.wait_settled().await;
+
+        let load = graph.get_load().await;
+        assert_eq!(load.get_sent(), vec![2, 1, 0, 0]);
+        assert_eq!(load.get_received(), vec![1, 0, 1, 1]);
+        assert_eq!(load.get_max(), 2);
+        assert_eq!(load.get_fairness(), 9. / 20.);
+
+        let edges = load.get_edges();
+        assert_eq!(edges.len(), 6);
+        assert!(edges.iter().all(|e| e.get_sent() == e.get_received()));
+        assert_eq!((edges[0].get_from(), edges[0].get_to()), (0, 1));
+        assert_eq!(edges[0].get_sent(), 0);
+    }
+}
@@ -1,12 +1,25 @@
 pub mod algorithms;
+pub mod byzantine;
 mod edge;
+mod engine;
+mod fingerprint;
 mod graph;
+mod load;
 mod message;
 mod node;
+mod plan;
+mod predict;
 mod routecache;
+mod seed;
+pub mod strategy;
 mod topology;
 
+pub use fingerprint::Fingerprint;
 pub use graph::Graph;
-pub use message::Message;
-pub use routecache::RouteCache;
-pub use topology::{FlowGraph, Topology};
+pub use load::{EdgeLoad, Load};
+pub use message::{BroadcastId, Message, MessageKind, Payload};
+pub use plan::RoutingPlan;
+pub use predict::{predict_flooding, predict_messages, Prediction};
+pub use routecache::{CacheStats, Counterexample, RouteCache, Shortfall};
+pub use seed::Seed;
+pub use topology::{FlowGraph, Placement, Topology};
@@ -0,0 +1,148 @@
+/// How the messages of a graph were spread over its nodes and edges.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Load {
+    sent: Vec<u64>,
+    received: Vec<u64>,
+    edges: Vec<EdgeLoad>,
+}
+
+/// The messages that went over the edge from one node to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EdgeLoad {
+    from: usize,
+    to: usize,
+    sent: u64,
+    received: u64,
+}
+
+impl EdgeLoad {
+    pub fn get_from(&self) -> usize {
+        self.from
+    }
+
+    pub fn get_to(&self) -> usize {
+        self.to
+    }
+
+    pub fn get_sent(&self) -> u64 {
+        self.sent
+    }
+
+    pub fn get_received(&self) -> u64 {
+        self.received
+    }
+}
+
+impl Load {
+    /// Collects the counts of every node, by label, and of every directed edge as
+    /// `(from, to, sent, received)`.
+    pub(crate) fn new(
+        sent: Vec<u64>,
+        received: Vec<u64>,
+        edges: Vec<(usize, usize, u64, u64)>,
+    ) -> Self {
+        let mut edges = edges
+            .into_iter()
+            .map(|(from, to, sent, received)| EdgeLoad {
+                from,
+                to,
+                sent,
+                received,
+            })
+            .collect::<Vec<EdgeLoad>>();
+        edges.sort_by_key(|e| (e.from, e.to));
+
+        Load {
+            sent,
+            received,
+            edges,
+        }
+    }
+
+    /// The messages sent by every node, by label.
+    pub fn get_sent(&self) -> Vec<u64> {
+        self.sent.clone()
+    }
+
+    /// The messages that arrived at every node, by label.
+    pub fn get_received(&self) -> Vec<u64> {
+        self.received.clone()
+    }
+
+    /// Every directed edge, ordered by its endpoints.
+    pub fn get_edges(&self) -> Vec<EdgeLoad> {
+        self.edges.clone()
+    }
+
+    /// The most messages sent by a single node.
+    pub fn get_max(&self) -> u64 {
+        self.sent.iter().copied().max().unwrap_or(0)
+    }
+
+    /// The messages sent per node.
+    pub fn get_mean(&self) -> f64 {
+        if self.sent.is_empty() {
+            return 0.;
+        }
+
+        self.sent.iter().sum::<u64>() as f64 / self.sent.len() as f64
+    }
+
+    /// Jain's fairness index of the messages sent per node, which is one when all
+    /// nodes send as many and `1 / n` when a single node sends everything.
+    pub fn get_fairness(&self) -> f64 {
+        let sum = self.sent.iter().sum::<u64>() as f64;
+        let squares = self.sent.iter().map(|&s| (s * s) as f64).sum::<f64>();
+
+        if squares == 0. {
+            return 1.;
+        }
+
+        sum * sum / (self.sent.len() as f64 * squares)
+    }
+
+    /// Adds the counts of another run on the same graph to these.
+    pub fn add(&mut self, other: &Load) {
+        if self.sent.is_empty() && self.edges.is_empty() {
+            *self = other.clone();
+            return;
+        }
+
+        assert_eq!(
+            self.sent.len(),
+            other.sent.len(),
+            "Only loads of the same graph can be added."
+        );
+
+        for (a, b) in self.sent.iter_mut().zip(&other.sent) {
+            *a += b;
+        }
+        for (a, b) in self.received.iter_mut().zip(&other.received) {
+            *a += b;
+        }
+        for (a, b) in self.edges.iter_mut().zip(&other.edges) {
+            a.sent += b.sent;
+            a.received += b.received;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fairness_of_even_and_skewed_loads() {
+        let even = Load::new(vec![2, 2, 2, 2], vec![2, 2, 2, 2], Vec::new());
+        assert_eq!(even.get_fairness(), 1.);
+        assert_eq!(even.get_mean(), 2.);
+
+        let mut skewed = Load::new(vec![8, 0, 0, 0], vec![0, 4, 2, 2], Vec::new());
+        assert_eq!(skewed.get_fairness(), 0.25);
+        assert_eq!(skewed.get_max(), 8);
+
+        skewed.add(&even);
+        assert_eq!(skewed.get_sent(), vec![10, 2, 2, 2]);
+        assert_eq!(skewed.get_received(), vec![2, 6, 4, 4]);
+    }
+}
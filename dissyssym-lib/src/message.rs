@@ -1,19 +1,156 @@
-#[derive(Debug, Clone)]
+use std::sync::Arc;
+
+/// Identifies a broadcast by the node that started it and its sequence number there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BroadcastId {
+    source: usize,
+    seq: u64,
+}
+
+impl BroadcastId {
+    pub fn new(source: usize, seq: u64) -> Self {
+        BroadcastId { source, seq }
+    }
+
+    pub fn get_source(&self) -> usize {
+        self.source
+    }
+
+    pub fn get_seq(&self) -> u64 {
+        self.seq
+    }
+}
+
+/// The role of a message within a broadcast.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageKind {
+    /// The content itself, as broadcast by its source.
+    Send,
+    /// The sender vouches for the content.
+    Echo,
+    /// The sender is ready to deliver the content.
+    Ready,
+    /// A kind defined by the wire format of an algorithm.
+    Custom(u8),
+}
+
+/// The opaque content of a broadcast.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Payload(Arc<[u8]>);
+
+impl Payload {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<Vec<u8>> for Payload {
+    fn from(bytes: Vec<u8>) -> Self {
+        Payload(bytes.into())
+    }
+}
+
+impl From<&[u8]> for Payload {
+    fn from(bytes: &[u8]) -> Self {
+        Payload(bytes.into())
+    }
+}
+
+impl From<&str> for Payload {
+    fn from(text: &str) -> Self {
+        Payload(text.as_bytes().into())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Message {
     sender: usize,
-    id: String,
+    broadcast: BroadcastId,
+    kind: MessageKind,
+    path: Option<Vec<usize>>,
+    payload: Payload,
 }
 
 impl Message {
-    pub fn new(sender: usize, id: String) -> Self {
-        Message { sender, id }
+    /// Creates the content of a broadcast, as sent by its source without any headers.
+    pub fn new(broadcast: BroadcastId, payload: impl Into<Payload>) -> Self {
+        Message {
+            sender: broadcast.get_source(),
+            broadcast,
+            kind: MessageKind::Send,
+            path: None,
+            payload: payload.into(),
+        }
+    }
+
+    /// Sets the node this message originates from, which differs from the source
+    /// of the broadcast for messages such as echoes.
+    pub fn with_sender(mut self, sender: usize) -> Self {
+        self.sender = sender;
+        self
+    }
+
+    pub fn with_broadcast(mut self, broadcast: BroadcastId) -> Self {
+        self.broadcast = broadcast;
+        self
+    }
+
+    pub fn with_kind(mut self, kind: MessageKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Adds a path header, the nodes the message passed before reaching the node that
+    /// sends it. Receivers append the neighbour they got it from themselves.
+    pub fn with_path(mut self, path: Vec<usize>) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    pub fn with_payload(mut self, payload: impl Into<Payload>) -> Self {
+        self.payload = payload.into();
+        self
     }
 
     pub fn get_sender(&self) -> usize {
         self.sender
     }
 
-    pub fn get_id(&self) -> String {
-        self.id.clone()
+    pub fn get_broadcast(&self) -> BroadcastId {
+        self.broadcast
+    }
+
+    pub fn get_kind(&self) -> MessageKind {
+        self.kind
+    }
+
+    pub fn get_path(&self) -> Option<Vec<usize>> {
+        self.path.clone()
+    }
+
+    pub fn get_payload(&self) -> Payload {
+        self.payload.clone()
+    }
+
+    /// The content as it was broadcast, without the headers added on the way.
+    pub fn get_content(&self) -> Message {
+        Message::new(self.broadcast, self.payload.clone())
+    }
+
+    /// The size of the message on the wire in bytes. Node labels and lengths take
+    /// four bytes, the sequence number eight, and the kind a single one.
+    pub fn size(&self) -> usize {
+        let header = 4 + 4 + 8 + 1;
+        let path = self.path.as_ref().map_or(0, |p| 4 + 4 * p.len());
+
+        header + path + 4 + self.payload.len()
     }
 }
@@ -1,11 +1,21 @@
 use std::{
+    collections::HashMap,
     fmt::Display,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
 };
 
 use tokio::sync::RwLock;
 
-use crate::{algorithms::Algorithm, edge::Edge, message::Message, RouteCache, Topology};
+use crate::{
+    algorithms::{Action, Algorithm},
+    byzantine::{ByzantineBehavior, SharedBehavior},
+    edge::Edge,
+    message::Message,
+    RoutingPlan, Topology,
+};
 
 #[derive(Debug)]
 pub struct Node<T>
@@ -14,59 +24,85 @@ where
 {
     label: usize,
     faulty: bool,
+    behavior: Option<SharedBehavior>,
     edges: Vec<Arc<RwLock<Edge<T>>>>,
-    algo: Arc<RwLock<T>>,
+    neighbours: HashMap<usize, usize>,
+    algo: Arc<Mutex<T>>,
     delivered: Vec<Message>,
+    received: AtomicU64,
 }
 
 impl<T: Algorithm + Send + Sync + 'static> Node<T> {
+    /// Creating the algorithm may block on generating routes, see [`crate::RouteCache::gen_routes`].
     pub fn new(
         label: usize,
         topology: Arc<Topology>,
-        route_cache: Arc<Mutex<RouteCache>>,
+        plan: Arc<RoutingPlan>,
     ) -> Option<Arc<RwLock<Self>>> {
-        let algo = match T::new(label, topology.clone(), route_cache) {
-            Some(a) => a,
-            None => return None,
-        };
+        let algo = T::new(label, topology.clone(), plan)?;
 
         Some(Arc::new(RwLock::new(Node {
             label,
             faulty: false,
-            algo: Arc::new(RwLock::new(algo)),
+            behavior: None,
+            algo: Arc::new(Mutex::new(algo)),
             edges: Vec::new(),
+            neighbours: HashMap::new(),
             delivered: Vec::new(),
+            received: AtomicU64::new(0),
         })))
     }
 
     pub async fn broadcast(node: Arc<RwLock<Node<T>>>, msg: Message) {
-        let locked = node.read().await;
-        let algo = locked.algo.clone();
-        drop(locked);
-
-        let mut algo = algo.write().await;
+        let algo = node.read().await.algo.clone();
+        let actions = algo.lock().unwrap().send_broadcast(msg);
 
-        algo.send_broadcast(node, msg).await
+        Self::apply(node, actions).await
     }
 
     pub async fn recv(node: Arc<RwLock<Self>>, sender: Arc<RwLock<Node<T>>>, msg: Message) {
+        let sender_label = sender.read().await.get_label();
         let node_lock = node.read().await;
         let algo = node_lock.algo.clone();
+        node_lock.received.fetch_add(1, Ordering::Relaxed);
 
-        if node_lock.faulty {
-            return;
+        if let Some(behavior) = &node_lock.behavior {
+            if !behavior.lock().unwrap().on_receive(sender_label, &msg) {
+                return;
+            }
         }
 
         drop(node_lock);
 
-        let mut algo = algo.write().await;
-        algo.on_message(node, sender, msg).await;
+        let actions = algo.lock().unwrap().on_message(sender_label, msg);
+        Self::apply(node, actions).await
     }
 
-    pub fn add_edge(&mut self, edge: Edge<T>) {
+    /// Carries out the actions of the algorithm, after its lock has been released.
+    async fn apply(node: Arc<RwLock<Self>>, actions: Vec<Action>) {
+        for action in actions {
+            match action {
+                Action::Send(to, msg) => {
+                    let edge = node.read().await.get_edge(to);
+                    let edge = edge.expect("The algorithm sent to a node that is not a neighbour.");
+
+                    edge.write().await.send(msg).await;
+                }
+                Action::Deliver(msg) => node.write().await.deliver(msg),
+            }
+        }
+    }
+
+    pub fn add_edge(&mut self, to: usize, edge: Edge<T>) {
+        self.neighbours.insert(to, self.edges.len());
         self.edges.push(Arc::new(RwLock::new(edge)));
     }
 
+    /// The edge towards the neighbour with label `to`.
+    pub fn get_edge(&self, to: usize) -> Option<Arc<RwLock<Edge<T>>>> {
+        self.neighbours.get(&to).map(|&i| self.edges[i].clone())
+    }
+
     pub fn get_edges(&self) -> &Vec<Arc<RwLock<Edge<T>>>> {
         &self.edges
     }
@@ -81,6 +117,21 @@ impl<T: Algorithm + Send + Sync + 'static> Node<T> {
         total
     }
 
+    /// The messages that arrived at this node, including those a faulty node drops.
+    pub fn get_received(&self) -> u64 {
+        self.received.load(Ordering::Relaxed)
+    }
+
+    pub async fn get_bytes(&self) -> u64 {
+        let mut total = 0;
+
+        for edge in &self.edges {
+            total += edge.read().await.get_bytes();
+        }
+
+        total
+    }
+
     pub fn deliver(&mut self, msg: Message) {
         self.delivered.push(msg);
     }
@@ -89,12 +140,29 @@ impl<T: Algorithm + Send + Sync + 'static> Node<T> {
         self.delivered.clone()
     }
 
+    pub fn get_delivered_count(&self) -> usize {
+        self.delivered.len()
+    }
+
+    /// What the node delivered after its first `before` deliveries.
+    pub(crate) fn get_delivered_since(&self, before: usize) -> &[Message] {
+        &self.delivered[before.min(self.delivered.len())..]
+    }
+
     pub fn get_faulty(&self) -> bool {
         self.faulty
     }
 
-    pub fn set_faulty(&mut self) {
+    /// Marks the node as faulty, acting according to `behavior` from now on.
+    pub async fn set_faulty(&mut self, behavior: Box<dyn ByzantineBehavior>) {
+        let behavior = Arc::new(Mutex::new(behavior));
+
+        for edge in &self.edges {
+            edge.write().await.set_behavior(behavior.clone());
+        }
+
         self.faulty = true;
+        self.behavior = Some(behavior);
     }
 
     pub fn get_label(&self) -> usize {
@@ -115,7 +183,7 @@ where
             return false;
         }
 
-        return true;
+        true
     }
 }
 
@@ -0,0 +1,75 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, OnceLock},
+};
+
+use crate::{topology::FlowGraph, RouteCache, Topology};
+
+type Routes = HashMap<usize, HashSet<usize>>;
+
+/// The routes of every source in a topology, shared by all nodes of a graph.
+///
+/// The routes are generated on first use, so algorithms that never route do not
+/// pay for them, and then only once for the whole graph rather than once per node.
+pub struct RoutingPlan {
+    topology: Arc<Topology>,
+    route_cache: Arc<RouteCache>,
+    routes: OnceLock<Option<HashMap<usize, Arc<Routes>>>>,
+}
+
+impl RoutingPlan {
+    pub fn new(topology: Arc<Topology>, route_cache: Arc<RouteCache>) -> Self {
+        RoutingPlan {
+            topology,
+            route_cache,
+            routes: OnceLock::new(),
+        }
+    }
+
+    pub fn get_topology(&self) -> Arc<Topology> {
+        self.topology.clone()
+    }
+
+    /// The routes of every source, or `None` if some source has none.
+    pub fn get_routes(&self) -> Option<&HashMap<usize, Arc<Routes>>> {
+        self.routes
+            .get_or_init(|| {
+                let nodes = FlowGraph::new(&self.topology.get_edges()).get_nodes();
+                let f = self.topology.get_faulty().len();
+
+                self.route_cache.gen_all_routes(&nodes, f)
+            })
+            .as_ref()
+    }
+
+    /// Collects, for every source, the neighbours node `n` relays its messages to.
+    pub fn get_table(&self, n: usize) -> Option<HashMap<usize, HashSet<usize>>> {
+        self.get_routes()?
+            .iter()
+            .map(|(&s, routes)| Some((s, routes.get(&n)?.clone())))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::Pathfind;
+
+    #[test]
+    fn plan_matches_the_cache() {
+        let top = Arc::new(Topology::complete(5, vec![4]));
+        let cache = Arc::new(RouteCache::new(Arc::new(Pathfind)));
+        let plan = RoutingPlan::new(top.clone(), cache.clone());
+        let nodes = FlowGraph::new(&top.get_edges()).get_nodes();
+
+        for s in 0..top.get_n() {
+            let routes = cache.gen_routes(&nodes, 1, s).unwrap();
+            assert!(Arc::ptr_eq(&plan.get_routes().unwrap()[&s], &routes));
+
+            for n in 0..top.get_n() {
+                assert_eq!(plan.get_table(n).unwrap()[&s], routes[&n]);
+            }
+        }
+    }
+}
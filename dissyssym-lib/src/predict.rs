@@ -0,0 +1,190 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{
+    strategy::{self, RouteStrategy},
+    topology::FlowGraph,
+    Topology,
+};
+
+/// The outcome of a benign broadcast, worked out without simulating it.
+///
+/// Faulty nodes are taken to be silent: they drop whatever they receive, but a
+/// faulty source still sends its broadcast to all of its neighbours.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Prediction {
+    forwards: Vec<u64>,
+    delivered: HashSet<usize>,
+}
+
+impl Prediction {
+    /// The messages sent over all edges.
+    pub fn get_total(&self) -> u64 {
+        self.forwards.iter().sum()
+    }
+
+    /// The messages sent by every node, indexed by label.
+    pub fn get_forwards(&self) -> Vec<u64> {
+        self.forwards.clone()
+    }
+
+    /// The nodes that deliver the broadcast, including the source.
+    pub fn get_delivered(&self) -> HashSet<usize> {
+        self.delivered.clone()
+    }
+}
+
+/// Predicts a broadcast of `RoutedAlgorithm` from `source`, over the routes that
+/// `strategy` builds to tolerate `f` faulty nodes. Returns `None` if the strategy
+/// finds no routes.
+///
+/// Every correct node relays only the first copy it receives, so the messages
+/// follow from which nodes the routes reach, whatever order the copies arrive in.
+pub fn predict_messages(
+    topology: &Topology,
+    strategy: &dyn RouteStrategy,
+    f: usize,
+    source: usize,
+) -> Option<Prediction> {
+    let nodes = FlowGraph::new(&topology.get_edges()).get_nodes();
+
+    // Like the cache, route the sources before this one first if their load counts.
+    let mut load = HashMap::new();
+    if strategy.uses_load() {
+        let mut before = nodes
+            .keys()
+            .copied()
+            .filter(|&s| s < source)
+            .collect::<Vec<_>>();
+        before.sort_unstable();
+
+        for s in before {
+            let routes = strategy.gen_routes_with_load(&nodes, f, s, &load)?;
+            strategy::add_load(&mut load, s, &routes);
+        }
+    }
+    let routes = strategy.gen_routes_with_load(&nodes, f, source, &load)?;
+
+    Some(spread(topology, source, |n, _| {
+        topology
+            .get_neighbours(n)
+            .into_iter()
+            .filter(|to| routes.get(&n).is_some_and(|route| route.contains(to)))
+            .collect()
+    }))
+}
+
+/// Predicts a broadcast of `FloodingAlgorithm` from `source`, in which every
+/// correct node relays to all of its neighbours but the one it first heard from.
+pub fn predict_flooding(topology: &Topology, source: usize) -> Prediction {
+    spread(topology, source, |n, from| {
+        topology
+            .get_neighbours(n)
+            .into_iter()
+            .filter(|&to| to != from)
+            .collect()
+    })
+}
+
+/// Follows the first copies of a broadcast from `source`, which sends to all of
+/// its neighbours, while a correct node `n` first hearing from `from` relays to
+/// `relays(n, from)`.
+fn spread(
+    topology: &Topology,
+    source: usize,
+    relays: impl Fn(usize, usize) -> Vec<usize>,
+) -> Prediction {
+    let faulty = topology.get_faulty();
+    let mut forwards = vec![0; topology.get_n()];
+    let mut delivered = HashSet::from([source]);
+    let mut queue = VecDeque::from([(source, topology.get_neighbours(source))]);
+
+    while let Some((node, to)) = queue.pop_front() {
+        forwards[node] = to.len() as u64;
+
+        for next in to {
+            if faulty.contains(&next) || !delivered.insert(next) {
+                continue;
+            }
+
+            queue.push_back((next, relays(next, node)));
+        }
+    }
+
+    Prediction {
+        forwards,
+        delivered,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        algorithms::{Algorithm, FloodingAlgorithm, RoutedAlgorithm},
+        strategy::Pathfind,
+        BroadcastId, Graph, Message, RouteCache, Seed,
+    };
+
+    /// Runs a broadcast from `source`, returning the messages every node sent and
+    /// the nodes that delivered.
+    async fn simulate<T: Algorithm + Send + Sync + 'static>(
+        topology: Arc<Topology>,
+        source: usize,
+    ) -> (Vec<u64>, HashSet<usize>) {
+        let cache = Arc::new(RouteCache::new(Arc::new(Pathfind)));
+        let mut graph: Graph<T> = Graph::new_simulated(topology, cache, Seed::new(7))
+            .await
+            .unwrap();
+
+        let sender = graph.get_nodes()[source].clone();
+        graph
+            .broadcast(sender, Message::new(BroadcastId::new(source, 0), "msg"))
+            .await;
+        graph.wait_settled().await;
+
+        let mut forwards = Vec::new();
+        let mut delivered = HashSet::new();
+        for node in graph.get_nodes() {
+            let node = node.read().await;
+            forwards.push(node.get_messages().await);
+
+            if !node.get_delivered().is_empty() {
+                delivered.insert(node.get_label());
+            }
+        }
+
+        (forwards, delivered)
+    }
+
+    #[tokio::test]
+    async fn predictions_match_the_simulation() {
+        // A ring of six with chords, where node 3 is faulty.
+        let edges = vec![
+            (0, 1),
+            (1, 2),
+            (2, 3),
+            (3, 4),
+            (4, 5),
+            (0, 5),
+            (0, 3),
+            (1, 4),
+            (2, 5),
+        ];
+        let topology = Arc::new(Topology::from_edges(edges, vec![3]));
+
+        for source in 0..topology.get_n() {
+            let routed = predict_messages(&topology, &Pathfind, 1, source).unwrap();
+            let (forwards, delivered) = simulate::<RoutedAlgorithm>(topology.clone(), source).await;
+            assert_eq!(routed.get_forwards(), forwards);
+            assert_eq!(routed.get_delivered(), delivered);
+
+            let flooding = predict_flooding(&topology, source);
+            let (forwards, delivered) =
+                simulate::<FloodingAlgorithm>(topology.clone(), source).await;
+            assert_eq!(flooding.get_forwards(), forwards);
+            assert_eq!(flooding.get_delivered(), delivered);
+        }
+    }
+}
@@ -1,47 +1,465 @@
 use std::{
     cmp::Reverse,
     collections::{HashMap, HashSet, VecDeque},
-    sync::Arc,
+    fmt::Display,
+    fs,
+    mem::size_of,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock,
+    },
 };
 
 use priority_queue::PriorityQueue;
 use sha2::{Digest, Sha512};
 
-use crate::topology::FlowGraph;
+use crate::{
+    fingerprint::{Fingerprint, GraphFingerprint},
+    strategy::{self, RouteStrategy},
+    topology::FlowGraph,
+};
+
+type Routes = HashMap<usize, HashSet<usize>>;
+
+/// The routes for one key, filled in by the first thread that asks for them.
+#[derive(Default)]
+struct Entry {
+    routes: OnceLock<Option<Arc<Routes>>>,
+    /// The tick of the cache clock at which the entry was last looked up.
+    used: AtomicU64,
+    /// The estimated bytes of the routes, zero until they are counted in the cache.
+    size: AtomicUsize,
+}
+
+/// The number of independently locked parts of the cache.
+const SHARDS: usize = 16;
+
+/// The version of the keys routes are stored under, which is part of the store
+/// directory so that a change of keys never leaves routes under stale names.
+/// Version 1 named routes by the full hash of the graph, version 2 by [`Fingerprint`].
+const KEY_FORMAT: u32 = 2;
+
+/// A set of faulty nodes for which some correct nodes miss a broadcast.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counterexample {
+    faulty: Vec<usize>,
+    undelivered: Vec<usize>,
+}
+
+/// A node that the routes of a source reach over too few node-disjoint paths.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shortfall {
+    target: usize,
+    paths: usize,
+    missing: usize,
+}
+
+impl Shortfall {
+    pub fn get_target(&self) -> usize {
+        self.target
+    }
 
+    /// The number of internally node-disjoint paths the routes do have.
+    pub fn get_paths(&self) -> usize {
+        self.paths
+    }
+
+    /// How many more paths are needed to tolerate `f` faulty nodes.
+    pub fn get_missing(&self) -> usize {
+        self.missing
+    }
+}
+
+impl Counterexample {
+    pub fn get_faulty(&self) -> Vec<usize> {
+        self.faulty.clone()
+    }
+
+    pub fn get_undelivered(&self) -> Vec<usize> {
+        self.undelivered.clone()
+    }
+}
+
+/// How well a route cache has done so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+    entries: usize,
+    bytes: usize,
+}
+
+impl CacheStats {
+    /// Lookups answered from memory, including those that waited for another
+    /// thread to generate the routes.
+    pub fn get_hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Lookups that had to load the routes from disk or generate them.
+    pub fn get_misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// Routes dropped from memory to stay within the budget.
+    pub fn get_evictions(&self) -> u64 {
+        self.evictions
+    }
+
+    pub fn get_entries(&self) -> usize {
+        self.entries
+    }
+
+    /// The estimated memory taken by the cached routes.
+    pub fn get_bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+impl Display for CacheStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} hits, {} misses, {} evictions, {} entries, {} KiB",
+            self.hits,
+            self.misses,
+            self.evictions,
+            self.entries,
+            self.bytes / 1024
+        )
+    }
+}
+
+/// Generates routes with a strategy, and remembers them.
+///
+/// The cache can be shared between threads as is. Keys are spread over shards
+/// that are only locked to look up a key, never while routes are generated, and
+/// threads asking for a key that is being generated wait for that key alone.
 pub struct RouteCache {
-    method: String,
-    cache: HashMap<String, Option<Arc<HashMap<usize, HashSet<usize>>>>>,
+    strategy: Arc<dyn RouteStrategy>,
+    shards: Vec<Mutex<HashMap<Fingerprint, Arc<Entry>>>>,
+    store: Option<PathBuf>,
+    budget: Option<usize>,
+    clock: AtomicU64,
+    bytes: AtomicUsize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
 }
 
 impl RouteCache {
-    pub fn new(method: String) -> Self {
+    pub fn new(strategy: Arc<dyn RouteStrategy>) -> Self {
         RouteCache {
-            method,
-            cache: HashMap::new(),
+            strategy,
+            shards: (0..SHARDS).map(|_| Mutex::default()).collect(),
+            store: None,
+            budget: None,
+            clock: AtomicU64::new(0),
+            bytes: AtomicUsize::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
         }
     }
 
+    /// Keeps the estimated memory of the cached routes below `bytes`, dropping the
+    /// least recently used routes first. Dropped routes are generated again, or
+    /// loaded if the cache has a store.
+    pub fn with_budget(mut self, bytes: usize) -> Self {
+        self.budget = Some(bytes);
+        self
+    }
+
+    /// Keeps the routes on disk as well, below `dir`, so later runs load them
+    /// instead of generating them again. Every strategy, version and key format
+    /// gets its own directory, so routes of an older version of a strategy are
+    /// never used.
+    pub fn with_store(mut self, dir: impl AsRef<Path>) -> Self {
+        let name = format!(
+            "{}-{}-k{}",
+            self.strategy.get_name(),
+            self.strategy.get_version(),
+            KEY_FORMAT
+        );
+        self.store = Some(dir.as_ref().join(name));
+        self
+    }
+
+    pub fn get_strategy(&self) -> Arc<dyn RouteStrategy> {
+        self.strategy.clone()
+    }
+
+    pub fn get_stats(&self) -> CacheStats {
+        let entries = self
+            .shards
+            .iter()
+            .map(|shard| {
+                shard
+                    .lock()
+                    .unwrap()
+                    .values()
+                    .filter(|entry| entry.routes.get().is_some())
+                    .count()
+            })
+            .sum();
+
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            entries,
+            bytes: self.bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Forgets all routes, including the ones stored on disk for this strategy.
+    /// Routes that are being generated meanwhile are still handed out and stored.
+    pub fn invalidate(&self) {
+        for shard in &self.shards {
+            for (_, entry) in shard.lock().unwrap().drain() {
+                self.bytes
+                    .fetch_sub(entry.size.load(Ordering::Relaxed), Ordering::Relaxed);
+            }
+        }
+
+        if let Some(store) = &self.store {
+            if store.exists() {
+                fs::remove_dir_all(store).expect("Failed to remove the route store.");
+            }
+        }
+    }
+
+    /// The routes of source `s`, generated at most once however many threads ask.
+    ///
+    /// This blocks while the routes are generated, by this or another thread, so
+    /// async code should call it through `tokio::task::spawn_blocking`.
     pub fn gen_routes(
-        &mut self,
+        &self,
         nodes: &HashMap<usize, HashSet<usize>>,
         f: usize,
         s: usize,
     ) -> Option<Arc<HashMap<usize, HashSet<usize>>>> {
-        let hash = Self::hash_params(nodes, f, s);
-
-        match self.cache.get(&hash) {
-            Some(routes) => routes.clone(),
-            None => {
-                let routes = match self.gen_routes_uncached(nodes, f, s) {
-                    Some(r) => Some(Arc::new(r)),
-                    None => None,
+        // Routes built on the load of the sources before `s` need those routed first.
+        if self.strategy.uses_load() {
+            return self.gen_routes_until(nodes, f, s)?.remove(&s);
+        }
+
+        self.lookup(Fingerprint::new(nodes, f, s), nodes, f, s, &HashMap::new())
+    }
+
+    /// Generates the routes of every source in the graph, hashing the graph once.
+    /// Returns `None` if any source has no routes.
+    ///
+    /// Sources are routed in ascending order, and strategies that balance the
+    /// relaying get the load of all sources before the current one.
+    pub fn gen_all_routes(
+        &self,
+        nodes: &HashMap<usize, HashSet<usize>>,
+        f: usize,
+    ) -> Option<HashMap<usize, Arc<Routes>>> {
+        self.gen_routes_until(nodes, f, usize::MAX)
+    }
+
+    /// Generates the routes of the sources up to and including `last`.
+    fn gen_routes_until(
+        &self,
+        nodes: &HashMap<usize, HashSet<usize>>,
+        f: usize,
+        last: usize,
+    ) -> Option<HashMap<usize, Arc<Routes>>> {
+        let graph = GraphFingerprint::new(nodes, f);
+        let mut sources = nodes
+            .keys()
+            .copied()
+            .filter(|&s| s <= last)
+            .collect::<Vec<usize>>();
+        sources.sort_unstable();
+
+        let mut load = HashMap::new();
+        sources
+            .into_iter()
+            .map(|s| {
+                if !self.strategy.uses_load() {
+                    let routes = self.lookup(graph.with_source(s), nodes, f, s, &load)?;
+                    return Some((s, routes));
+                }
+
+                let routes = self.lookup(graph.with_load(s, &load), nodes, f, s, &load)?;
+                strategy::add_load(&mut load, s, &routes);
+
+                Some((s, routes))
+            })
+            .collect()
+    }
+
+    fn lookup(
+        &self,
+        key: Fingerprint,
+        nodes: &HashMap<usize, HashSet<usize>>,
+        f: usize,
+        s: usize,
+        load: &HashMap<usize, usize>,
+    ) -> Option<Arc<Routes>> {
+        // The shard is only locked to find the entry, which is filled outside of it.
+        let shard = &self.shards[key.get_byte(0) as usize % SHARDS];
+        let entry = shard.lock().unwrap().entry(key).or_default().clone();
+        entry.used.store(
+            self.clock.fetch_add(1, Ordering::Relaxed),
+            Ordering::Relaxed,
+        );
+
+        let mut filled = false;
+        let routes = entry
+            .routes
+            .get_or_init(|| {
+                filled = true;
+
+                let routes = match self.load(key) {
+                    Some(routes) => routes,
+                    None => {
+                        let routes = self.strategy.gen_routes_with_load(nodes, f, s, load);
+                        self.save(key, routes.as_ref());
+
+                        routes
+                    }
                 };
-                self.cache.insert(hash, routes.clone());
 
-                routes
+                routes.map(Arc::new)
+            })
+            .clone();
+
+        if !filled {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return routes;
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        // Only routes that are still cached count, not ones invalidated meanwhile.
+        let size = Self::size_of(routes.as_deref());
+        if let Some(cached) = shard.lock().unwrap().get(&key) {
+            if Arc::ptr_eq(cached, &entry) {
+                entry.size.store(size, Ordering::Relaxed);
+                self.bytes.fetch_add(size, Ordering::Relaxed);
             }
         }
+        self.evict();
+
+        routes
+    }
+
+    /// Drops the least recently used routes once the cache is over its budget.
+    ///
+    /// Finding those sorts every entry, so the cache is shrunk to nine tenths of
+    /// its budget at once, and the next misses fit without evicting anything.
+    fn evict(&self) {
+        let budget = match self.budget {
+            Some(budget) => budget,
+            None => return,
+        };
+
+        if self.bytes.load(Ordering::Relaxed) <= budget {
+            return;
+        }
+        let low_water = budget / 10 * 9;
+
+        let mut entries = Vec::new();
+        for (i, shard) in self.shards.iter().enumerate() {
+            for (key, entry) in shard.lock().unwrap().iter() {
+                if entry.size.load(Ordering::Relaxed) > 0 {
+                    entries.push((entry.used.load(Ordering::Relaxed), i, *key));
+                }
+            }
+        }
+        entries.sort_unstable();
+
+        for (_, i, key) in entries {
+            if self.bytes.load(Ordering::Relaxed) <= low_water {
+                break;
+            }
+
+            if let Some(entry) = self.shards[i].lock().unwrap().remove(&key) {
+                self.bytes
+                    .fetch_sub(entry.size.load(Ordering::Relaxed), Ordering::Relaxed);
+                self.evictions.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Roughly estimates the memory taken by a set of routes.
+    fn size_of(routes: Option<&Routes>) -> usize {
+        let routes = routes.map_or(0, |routes| {
+            routes
+                .values()
+                .map(|route| {
+                    size_of::<usize>()
+                        + size_of::<HashSet<usize>>()
+                        + route.len() * size_of::<usize>()
+                })
+                .sum()
+        });
+
+        size_of::<Entry>() + size_of::<Routes>() + routes
+    }
+
+    /// Reads the routes stored under `key`, where `Some(None)` means the strategy
+    /// found none. Missing or unreadable files count as a miss.
+    fn load(&self, key: Fingerprint) -> Option<Option<Routes>> {
+        let content = fs::read_to_string(self.store.as_ref()?.join(key.to_string())).ok()?;
+
+        if content.trim() == "none" {
+            return Some(None);
+        }
+
+        let mut routes = HashMap::new();
+        for line in content.lines() {
+            let (node, route) = line.split_once(':')?;
+            let route = route
+                .split(',')
+                .filter(|r| !r.is_empty())
+                .map(|r| r.parse::<usize>().ok())
+                .collect::<Option<HashSet<usize>>>()?;
+
+            routes.insert(node.parse::<usize>().ok()?, route);
+        }
+
+        Some(Some(routes))
+    }
+
+    /// Writes the routes through to disk, if this cache has a store. The file is
+    /// moved into place at once, so readers never see half of it.
+    fn save(&self, key: Fingerprint, routes: Option<&Routes>) {
+        let store = match &self.store {
+            Some(store) => store,
+            None => return,
+        };
+
+        let content = match routes {
+            Some(routes) => {
+                let mut nodes = routes.keys().copied().collect::<Vec<usize>>();
+                nodes.sort_unstable();
+
+                nodes
+                    .into_iter()
+                    .map(|n| {
+                        let mut route = routes[&n].iter().copied().collect::<Vec<usize>>();
+                        route.sort_unstable();
+                        let route = route.iter().map(|r| r.to_string()).collect::<Vec<_>>();
+
+                        format!("{}:{}\n", n, route.join(","))
+                    })
+                    .collect::<String>()
+            }
+            None => "none\n".to_string(),
+        };
+
+        fs::create_dir_all(store).expect("Failed to create the route store.");
+        let temp = store.join(format!("{}.{}.tmp", key, std::process::id()));
+        fs::write(&temp, content).expect("Failed to write routes to the store.");
+        fs::rename(&temp, store.join(key.to_string()))
+            .expect("Failed to move routes into the store.");
     }
 
     pub fn gen_routes_uncached(
@@ -50,11 +468,7 @@ impl RouteCache {
         f: usize,
         s: usize,
     ) -> Option<HashMap<usize, HashSet<usize>>> {
-        match self.method.as_str() {
-            "pathfind" => Self::gen_method_pathfind(nodes, f, s),
-            "unreliable" => Self::gen_method_unreliable(nodes, f, s),
-            _ => panic!("Invalid path generation method!"),
-        }
+        self.strategy.gen_routes(nodes, f, s)
     }
 
     pub fn gen_method_pathfind(
@@ -66,7 +480,7 @@ impl RouteCache {
         let mut used = HashMap::new();
         let mut accepted = HashMap::new();
 
-        for (&n, _) in nodes {
+        for &n in nodes.keys() {
             routes.insert(n, HashSet::new());
             used.insert(n, HashSet::new());
             accepted.insert(n, 0);
@@ -90,10 +504,7 @@ impl RouteCache {
                 None => break,
             };
 
-            let path = match Self::gen_method_pathfind_path(s, t, nodes, &used, &routes) {
-                Some(p) => p,
-                None => return None,
-            };
+            let path = Self::gen_method_pathfind_path(s, t, nodes, &used, &routes)?;
 
             accepted.insert(t, accepted.get(&t).unwrap() + 1);
 
@@ -117,8 +528,12 @@ impl RouteCache {
             return None;
         }
 
+        // Visit in a fixed order, so ties are broken the same way on every run.
+        let mut ordered = consider.iter().copied().collect::<Vec<usize>>();
+        ordered.sort_unstable();
+
         let mut best = None;
-        for &i in &consider {
+        for i in ordered {
             if coloured.contains(&i) {
                 continue;
             }
@@ -167,12 +582,12 @@ impl RouteCache {
             let mut best = None;
 
             for path in &potential {
-                let conn = Self::gen_method_pathfind_partial_connectivity(nodes, used, &path, s, t);
+                let conn = Self::gen_method_pathfind_partial_connectivity(nodes, used, path, s, t);
                 let mut overlap = 0;
 
                 for other in &potential {
-                    for i in 1..(other.len() - 1) {
-                        if path.contains(&other[i]) {
+                    for node in &other[1..(other.len() - 1)] {
+                        if path.contains(node) {
                             overlap += 1;
                         }
                     }
@@ -236,7 +651,10 @@ impl RouteCache {
                 paths.push(path.clone());
             }
 
-            for &neigh in nodes.get(&last).unwrap() {
+            let mut neighbours = nodes.get(&last).unwrap().iter().collect::<Vec<&usize>>();
+            neighbours.sort_unstable();
+
+            for &neigh in neighbours {
                 if path.contains(&neigh) {
                     continue;
                 }
@@ -258,7 +676,7 @@ impl RouteCache {
     fn gen_method_pathfind_partial_connectivity(
         nodes: &HashMap<usize, HashSet<usize>>,
         used: &HashMap<usize, HashSet<usize>>,
-        proposed: &Vec<usize>,
+        proposed: &[usize],
         s: usize,
         t: usize,
     ) -> usize {
@@ -287,7 +705,7 @@ impl RouteCache {
             }
         }
 
-        FlowGraph::new(&edges).max_flow(s, t)
+        FlowGraph::new(&edges).max_edge_flow(s, t)
     }
 
     pub fn gen_method_unreliable(
@@ -298,7 +716,7 @@ impl RouteCache {
         let mut q = VecDeque::new();
         let mut node_paths: HashMap<usize, Vec<Vec<usize>>> = HashMap::new();
 
-        for (n, _) in nodes {
+        for n in nodes.keys() {
             node_paths.insert(*n, Vec::new());
         }
 
@@ -319,7 +737,7 @@ impl RouteCache {
 
                     for op in other_paths {
                         for path_item in &path {
-                            if op.contains(&path_item) && *path_item != s {
+                            if op.contains(path_item) && *path_item != s {
                                 valid = false;
                                 break;
                             }
@@ -372,7 +790,7 @@ impl RouteCache {
         }
 
         let mut routes = HashMap::new();
-        for (&n, _) in nodes {
+        for &n in nodes.keys() {
             let mut to = HashSet::new();
 
             for neigh in nodes.get(&n).unwrap() {
@@ -397,24 +815,127 @@ impl RouteCache {
         Some(routes)
     }
 
-    fn hash_params(nodes: &HashMap<usize, HashSet<usize>>, f: usize, s: usize) -> String {
-        let mut hasher = Sha512::new();
-        let mut nodes = nodes
-            .iter()
-            .map(|(k, v)| {
-                let mut items = v.iter().map(|v| v.to_string()).collect::<Vec<String>>();
-                items.sort();
+    /// The correct nodes that never receive a broadcast of `s` when every correct
+    /// node relays it over `routes`, and the nodes in `faulty` relay nothing.
+    pub fn undelivered(
+        nodes: &HashMap<usize, HashSet<usize>>,
+        routes: &HashMap<usize, HashSet<usize>>,
+        s: usize,
+        faulty: &HashSet<usize>,
+    ) -> Vec<usize> {
+        let mut reached = HashSet::from([s]);
+        let mut q = VecDeque::new();
+
+        // The source itself sends to all of its neighbours.
+        for &neigh in nodes.get(&s).unwrap() {
+            if reached.insert(neigh) && !faulty.contains(&neigh) {
+                q.push_back(neigh);
+            }
+        }
+
+        while let Some(n) = q.pop_front() {
+            for &to in routes.get(&n).unwrap() {
+                if reached.insert(to) && !faulty.contains(&to) {
+                    q.push_back(to);
+                }
+            }
+        }
 
-                format!("{}-{}", k, items.join(","))
+        let mut missing = nodes
+            .keys()
+            .copied()
+            .filter(|n| !reached.contains(n) && !faulty.contains(n))
+            .collect::<Vec<usize>>();
+        missing.sort_unstable();
+
+        missing
+    }
+
+    /// Checks that `routes` contain `f + 1` internally node-disjoint paths from `s`
+    /// to every other node, and lists the nodes that fall short. By Menger's
+    /// theorem, routes without shortfalls tolerate any `f` crashed relays.
+    ///
+    /// Neighbours that `s` sends to directly trust its copy, so they need no
+    /// further paths.
+    pub fn verify_routes(
+        routes: &HashMap<usize, HashSet<usize>>,
+        f: usize,
+        s: usize,
+    ) -> Vec<Shortfall> {
+        let flowgraph = FlowGraph::directed(routes);
+        let mut targets = flowgraph
+            .get_nodes()
+            .into_keys()
+            .filter(|&t| t != s)
+            .filter(|t| !routes.get(&s).is_some_and(|direct| direct.contains(t)))
+            .collect::<Vec<usize>>();
+        targets.sort_unstable();
+
+        targets
+            .into_iter()
+            .filter_map(|target| {
+                let paths = flowgraph.max_vertex_flow(s, target);
+
+                match paths > f {
+                    true => None,
+                    false => Some(Shortfall {
+                        target,
+                        paths,
+                        missing: f + 1 - paths,
+                    }),
+                }
             })
-            .collect::<Vec<String>>();
-        nodes.sort();
+            .collect()
+    }
+
+    /// Tries every set of `f` faulty nodes other than `s`, and returns the first
+    /// one for which some correct node never receives the broadcast of `s` over
+    /// `routes`. Without a counterexample the routes tolerate any `f` crashes.
+    ///
+    /// There are `(n - 1) choose f` sets, so this is only feasible for small `n`.
+    pub fn find_counterexample(
+        nodes: &HashMap<usize, HashSet<usize>>,
+        routes: &HashMap<usize, HashSet<usize>>,
+        f: usize,
+        s: usize,
+    ) -> Option<Counterexample> {
+        let mut candidates = nodes
+            .keys()
+            .copied()
+            .filter(|&n| n != s)
+            .collect::<Vec<usize>>();
+        candidates.sort_unstable();
+
+        let f = f.min(candidates.len());
+        let mut picked = (0..f).collect::<Vec<usize>>();
+
+        loop {
+            let faulty = picked
+                .iter()
+                .map(|&i| candidates[i])
+                .collect::<HashSet<usize>>();
+            let undelivered = Self::undelivered(nodes, routes, s, &faulty);
+
+            if !undelivered.is_empty() {
+                let mut faulty = faulty.into_iter().collect::<Vec<usize>>();
+                faulty.sort_unstable();
+
+                return Some(Counterexample {
+                    faulty,
+                    undelivered,
+                });
+            }
 
-        hasher.update(nodes.join("|"));
-        hasher.update(f.to_string());
-        hasher.update(s.to_string());
+            // Move on to the next combination, in lexicographic order.
+            let i = (0..f)
+                .rev()
+                .find(|&i| picked[i] < candidates.len() - f + i)?;
+            picked[i] += 1;
 
-        format!("{:X}", hasher.finalize())
+            for j in (i + 1)..f {
+                picked[j] = picked[j - 1] + 1;
+            }
+        }
     }
 }
 
@@ -422,6 +943,39 @@ impl RouteCache {
 mod tests {
     use super::*;
 
+    #[test]
+    fn counterexample_for_single_relay() {
+        // A ring of four, where node 1 alone relays to node 2.
+        let nodes = HashMap::from([
+            (0, HashSet::from([1, 3])),
+            (1, HashSet::from([0, 2])),
+            (2, HashSet::from([1, 3])),
+            (3, HashSet::from([0, 2])),
+        ]);
+        let routes = HashMap::from([
+            (0, HashSet::from([1, 3])),
+            (1, HashSet::from([2])),
+            (2, HashSet::new()),
+            (3, HashSet::new()),
+        ]);
+
+        let counterexample = RouteCache::find_counterexample(&nodes, &routes, 1, 0).unwrap();
+        assert_eq!(counterexample.get_faulty(), vec![1]);
+        assert_eq!(counterexample.get_undelivered(), vec![2]);
+
+        let shortfalls = RouteCache::verify_routes(&routes, 1, 0);
+        assert_eq!(shortfalls.len(), 1);
+        assert_eq!(shortfalls[0].get_target(), 2);
+        assert_eq!(shortfalls[0].get_missing(), 1);
+
+        let mut routes = routes;
+        routes.insert(3, HashSet::from([2]));
+        assert_eq!(RouteCache::find_counterexample(&nodes, &routes, 1, 0), None);
+
+        // Nodes 1 and 3 hear from the source itself, and node 2 over both of them.
+        assert_eq!(RouteCache::verify_routes(&routes, 1, 0), vec![]);
+    }
+
     #[test]
     fn pathfind_connectivity_cutoff() {
         let mut nodes = HashMap::new();
@@ -448,13 +1002,13 @@ mod tests {
         ]);
 
         let connect =
-            RouteCache::gen_method_pathfind_partial_connectivity(&nodes, &used, &vec![], 3, 1);
+            RouteCache::gen_method_pathfind_partial_connectivity(&nodes, &used, &[], 3, 1);
         assert_eq!(connect, 2);
 
         let connect = RouteCache::gen_method_pathfind_partial_connectivity(
             &nodes,
             &used,
-            &vec![3, 0, 4, 1],
+            &[3, 0, 4, 1],
             3,
             1,
         );
@@ -463,10 +1017,114 @@ mod tests {
         let connect = RouteCache::gen_method_pathfind_partial_connectivity(
             &nodes,
             &used,
-            &vec![3, 0, 6, 1],
+            &[3, 0, 6, 1],
             3,
             1,
         );
         assert_eq!(connect, 1);
     }
+
+    /// Counts how often it has to build routes.
+    struct Counting(std::sync::atomic::AtomicUsize);
+
+    impl RouteStrategy for Counting {
+        fn get_name(&self) -> &str {
+            "counting"
+        }
+
+        fn gen_routes(&self, nodes: &Routes, f: usize, s: usize) -> Option<Routes> {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            RouteCache::gen_method_unreliable(nodes, f, s)
+        }
+    }
+
+    #[test]
+    fn store_survives_the_cache() {
+        let dir = std::env::temp_dir().join(format!("dissyssym-routes-{}", std::process::id()));
+        let strategy = Arc::new(Counting(Default::default()));
+        let calls = || strategy.0.load(std::sync::atomic::Ordering::Relaxed);
+        let nodes = FlowGraph::new(&vec![(0, 1), (1, 2), (2, 3), (0, 3)]).get_nodes();
+
+        let cache = RouteCache::new(strategy.clone()).with_store(&dir);
+        let routes = cache.gen_routes(&nodes, 1, 0);
+        assert_eq!(calls(), 1);
+
+        // A new cache loads the routes written through by the first one.
+        let cache = RouteCache::new(strategy.clone()).with_store(&dir);
+        assert_eq!(cache.gen_routes(&nodes, 1, 0), routes);
+        assert_eq!(calls(), 1);
+
+        cache.invalidate();
+        assert_eq!(cache.gen_routes(&nodes, 1, 0), routes);
+        assert_eq!(calls(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn concurrent_lookups_generate_once() {
+        let strategy = Arc::new(Counting(Default::default()));
+        let cache = RouteCache::new(strategy.clone());
+        let nodes = FlowGraph::new(&vec![(0, 1), (1, 2), (2, 3), (0, 3), (0, 2)]).get_nodes();
+        let barrier = std::sync::Barrier::new(8);
+
+        let routes = std::thread::scope(|scope| {
+            let handles = (0..8)
+                .map(|i| {
+                    let (cache, nodes, barrier) = (&cache, &nodes, &barrier);
+                    scope.spawn(move || {
+                        barrier.wait();
+                        (i % 2, cache.gen_routes(nodes, 1, i % 2))
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        // Every thread got the routes of its sender, built once per sender.
+        assert_eq!(strategy.0.load(std::sync::atomic::Ordering::Relaxed), 2);
+        for (s, r) in routes {
+            assert_eq!(r, cache.gen_routes(&nodes, 1, s));
+        }
+    }
+
+    #[test]
+    fn budget_evicts_least_recently_used() {
+        let strategy = Arc::new(Counting(Default::default()));
+        let calls = || strategy.0.load(std::sync::atomic::Ordering::Relaxed);
+        let nodes = FlowGraph::new(&vec![(0, 1), (1, 2), (2, 3), (0, 3), (0, 2)]).get_nodes();
+
+        // Room for two sets of routes, but not for three.
+        let size = RouteCache::size_of(
+            RouteCache::new(strategy.clone())
+                .gen_routes(&nodes, 1, 0)
+                .as_deref(),
+        );
+        let cache = RouteCache::new(strategy.clone()).with_budget(size * 5 / 2);
+        let calls_before = calls();
+
+        cache.gen_routes(&nodes, 1, 0);
+        cache.gen_routes(&nodes, 1, 1);
+        cache.gen_routes(&nodes, 1, 0);
+        cache.gen_routes(&nodes, 1, 2);
+        assert_eq!(calls() - calls_before, 3);
+
+        let stats = cache.get_stats();
+        assert_eq!((stats.get_hits(), stats.get_misses()), (1, 3));
+        assert_eq!((stats.get_entries(), stats.get_evictions()), (2, 1));
+        assert!(stats.get_bytes() <= size * 5 / 2 / 10 * 9);
+
+        // Source 1 was used least recently, so it went first.
+        cache.gen_routes(&nodes, 1, 0);
+        assert_eq!(calls() - calls_before, 3);
+        cache.gen_routes(&nodes, 1, 1);
+        assert_eq!(calls() - calls_before, 4);
+
+        cache.invalidate();
+        assert_eq!(cache.get_stats().get_bytes(), 0);
+    }
 }
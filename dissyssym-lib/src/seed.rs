@@ -0,0 +1,77 @@
+use std::{fmt::Display, num::ParseIntError, str::FromStr};
+
+use rand::{rngs::StdRng, SeedableRng};
+use sha2::{Digest, Sha512};
+
+/// Seed of an experiment, from which every random component derives its own stream.
+///
+/// Streams are derived by name, so adding randomness to one component never shifts
+/// the numbers drawn by another, and any result can be regenerated from its seed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Seed(u64);
+
+impl Seed {
+    pub fn new(seed: u64) -> Self {
+        Seed(seed)
+    }
+
+    /// Picks a fresh seed, for when the experiment does not specify one.
+    pub fn random() -> Self {
+        Seed(rand::random())
+    }
+
+    /// Parses the seed from the `n`-th command line argument, or picks a random one
+    /// if it is not given.
+    pub fn from_arg(n: usize) -> Self {
+        match std::env::args().nth(n) {
+            Some(s) => s
+                .parse::<Seed>()
+                .expect("Failed to parse the experiment seed."),
+            None => Seed::random(),
+        }
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0
+    }
+
+    /// Derives the seed of a sub-experiment, such as a single topology and `f`.
+    pub fn derive(&self, label: &str) -> Seed {
+        let digest = self.digest(label);
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&digest[..8]);
+
+        Seed(u64::from_le_bytes(bytes))
+    }
+
+    /// Creates the random generator for the named component.
+    pub fn rng(&self, stream: &str) -> StdRng {
+        let digest = self.digest(stream);
+        let mut bytes = [0; 32];
+        bytes.copy_from_slice(&digest[..32]);
+
+        StdRng::from_seed(bytes)
+    }
+
+    fn digest(&self, label: &str) -> Vec<u8> {
+        let mut hasher = Sha512::new();
+        hasher.update(self.0.to_le_bytes());
+        hasher.update(label);
+
+        hasher.finalize().to_vec()
+    }
+}
+
+impl Display for Seed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for Seed {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Seed(s.parse()?))
+    }
+}
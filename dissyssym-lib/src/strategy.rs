@@ -0,0 +1,928 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use sha2::{Digest, Sha512};
+
+use crate::{FlowGraph, RouteCache, Topology};
+
+/// A way of building the route tables of a broadcast source.
+///
+/// Implement this to use routes built outside of this crate with a [`RouteCache`].
+pub trait RouteStrategy: Send + Sync {
+    /// The name the strategy is selected and reported by.
+    fn get_name(&self) -> &str;
+
+    /// Tells apart the routes of different versions or settings of the strategy,
+    /// so that routes stored by an older one are not used. Change it whenever the
+    /// strategy builds different routes.
+    fn get_version(&self) -> String {
+        "1".to_string()
+    }
+
+    /// Builds, for every node, the neighbours it relays the broadcasts of `s` to,
+    /// such that every node is reached despite `f` faulty nodes.
+    fn gen_routes(
+        &self,
+        nodes: &HashMap<usize, HashSet<usize>>,
+        f: usize,
+        s: usize,
+    ) -> Option<HashMap<usize, HashSet<usize>>>;
+
+    /// Whether the routes of a source depend on the relaying of the sources routed
+    /// before it, in which case they are built by [`RouteStrategy::gen_routes_with_load`].
+    fn uses_load(&self) -> bool {
+        false
+    }
+
+    /// Builds the routes of `s` like [`RouteStrategy::gen_routes`], given how many
+    /// neighbours every node relays to already for the sources routed before it.
+    fn gen_routes_with_load(
+        &self,
+        nodes: &HashMap<usize, HashSet<usize>>,
+        f: usize,
+        s: usize,
+        _load: &HashMap<usize, usize>,
+    ) -> Option<HashMap<usize, HashSet<usize>>> {
+        self.gen_routes(nodes, f, s)
+    }
+}
+
+/// Grows `f + 1` disjoint paths to every node, preferring paths over edges that
+/// are routed already.
+#[derive(Debug, Default)]
+pub struct Pathfind;
+
+impl RouteStrategy for Pathfind {
+    fn get_name(&self) -> &str {
+        "pathfind"
+    }
+
+    fn gen_routes(
+        &self,
+        nodes: &HashMap<usize, HashSet<usize>>,
+        f: usize,
+        s: usize,
+    ) -> Option<HashMap<usize, HashSet<usize>>> {
+        RouteCache::gen_method_pathfind(nodes, f, s)
+    }
+}
+
+/// Spreads paths breadth-first from the source, which is fast but does not always
+/// find `f + 1` disjoint paths.
+#[derive(Debug, Default)]
+pub struct Unreliable;
+
+impl RouteStrategy for Unreliable {
+    fn get_name(&self) -> &str {
+        "unreliable"
+    }
+
+    fn gen_routes(
+        &self,
+        nodes: &HashMap<usize, HashSet<usize>>,
+        f: usize,
+        s: usize,
+    ) -> Option<HashMap<usize, HashSet<usize>>> {
+        RouteCache::gen_method_unreliable(nodes, f, s)
+    }
+}
+
+/// Builds `f + 1` node-disjoint paths of least total length to every node in turn,
+/// with Suurballe's and Bhandari's successive shortest paths, in polynomial time.
+///
+/// Edges that are routed already cost `shared` and new ones `new`, so the lower
+/// `shared` is against `new`, the longer the paths to later nodes may get to share
+/// them. By default an edge in use costs two thirds of a new one, which keeps paths
+/// short but prefers sharing among paths of about the same length.
+#[derive(Debug, Clone, Copy)]
+pub struct Suurballe {
+    shared: u64,
+    new: u64,
+}
+
+impl Suurballe {
+    pub fn new(shared: u64, new: u64) -> Self {
+        Suurballe { shared, new }
+    }
+
+    /// The costs of an edge that is routed already and of a new one.
+    pub fn get_costs(&self) -> (u64, u64) {
+        (self.shared, self.new)
+    }
+}
+
+impl Default for Suurballe {
+    fn default() -> Self {
+        Suurballe::new(2, 3)
+    }
+}
+
+impl RouteStrategy for Suurballe {
+    fn get_name(&self) -> &str {
+        "suurballe"
+    }
+
+    fn get_version(&self) -> String {
+        format!("1-{}-{}", self.shared, self.new)
+    }
+
+    fn gen_routes(
+        &self,
+        nodes: &HashMap<usize, HashSet<usize>>,
+        f: usize,
+        s: usize,
+    ) -> Option<HashMap<usize, HashSet<usize>>> {
+        cheapest_routes(nodes, f, s, |_, b, relays| match relays.contains(&b) {
+            true => self.shared,
+            false => self.new,
+        })
+    }
+}
+
+/// Builds `f + 1` node-disjoint paths to every node in turn with a min-cost flow in
+/// which edges that are routed already are free and new ones cost one.
+///
+/// This minimises the edges added for every node, which is what the message count
+/// of the routed algorithm consists of, no matter how long the paths get.
+#[derive(Debug, Default)]
+pub struct MinCost;
+
+impl RouteStrategy for MinCost {
+    fn get_name(&self) -> &str {
+        "mincost"
+    }
+
+    fn gen_routes(
+        &self,
+        nodes: &HashMap<usize, HashSet<usize>>,
+        f: usize,
+        s: usize,
+    ) -> Option<HashMap<usize, HashSet<usize>>> {
+        cheapest_routes(nodes, f, s, |_, b, relays| match relays.contains(&b) {
+            true => 0,
+            false => 1,
+        })
+    }
+}
+
+/// Builds `f + 1` node-disjoint paths to every node in turn like [`MinCost`], but a
+/// new edge costs one more for every neighbour its relay serves already, for this
+/// source and for the sources [`RouteCache::gen_all_routes`] routed before it.
+///
+/// Minimising route edges alone tends to funnel the traffic through a few hubs,
+/// which then send most of the messages. This spreads the relaying over more
+/// nodes, at the price of a few more messages per broadcast.
+#[derive(Debug, Default)]
+pub struct Balanced;
+
+impl RouteStrategy for Balanced {
+    fn get_name(&self) -> &str {
+        "balanced"
+    }
+
+    fn get_version(&self) -> String {
+        "2".to_string()
+    }
+
+    fn gen_routes(
+        &self,
+        nodes: &HashMap<usize, HashSet<usize>>,
+        f: usize,
+        s: usize,
+    ) -> Option<HashMap<usize, HashSet<usize>>> {
+        self.gen_routes_with_load(nodes, f, s, &HashMap::new())
+    }
+
+    fn uses_load(&self) -> bool {
+        true
+    }
+
+    fn gen_routes_with_load(
+        &self,
+        nodes: &HashMap<usize, HashSet<usize>>,
+        f: usize,
+        s: usize,
+        load: &HashMap<usize, usize>,
+    ) -> Option<HashMap<usize, HashSet<usize>>> {
+        cheapest_routes(nodes, f, s, |a, b, relays| match relays.contains(&b) {
+            true => 0,
+            false => 1 + (load.get(&a).copied().unwrap_or(0) + relays.len()) as u64,
+        })
+    }
+}
+
+/// Builds `f + 1` node-disjoint paths to every node in turn over which copies
+/// arrive fast, given the expected latency of every edge.
+///
+/// A node waits for its slowest path when `f` others crash, so the latency of the
+/// slowest of its paths is kept low. The smallest bound on the path latency is
+/// searched for, such that the cheapest paths over the edges that lie on a path
+/// within the bound all stay within it. Every edge that is not routed yet costs
+/// `tradeoff` times the mean latency on top, so a larger trade-off sends fewer
+/// messages over slower paths. The routes depend on the latencies, so a
+/// [`RouteCache`] with this strategy should only be used for the topology it was
+/// made from.
+#[derive(Debug, Clone)]
+pub struct Latency {
+    latencies: HashMap<(usize, usize), Duration>,
+    tradeoff: f64,
+}
+
+impl Latency {
+    /// Takes the latencies of the edges of `topology`.
+    pub fn new(topology: &Topology, tradeoff: f64) -> Self {
+        Latency {
+            latencies: topology
+                .get_edges()
+                .into_iter()
+                .map(|(a, b)| ((a.min(b), a.max(b)), topology.get_latency(a, b)))
+                .collect(),
+            tradeoff,
+        }
+    }
+
+    pub fn get_tradeoff(&self) -> f64 {
+        self.tradeoff
+    }
+
+    fn get_latency(&self, a: usize, b: usize) -> Duration {
+        self.latencies
+            .get(&(a.min(b), a.max(b)))
+            .copied()
+            .unwrap_or(Topology::DEFAULT_LATENCY)
+    }
+}
+
+impl RouteStrategy for Latency {
+    fn get_name(&self) -> &str {
+        "latency"
+    }
+
+    fn get_version(&self) -> String {
+        let mut latencies = self
+            .latencies
+            .iter()
+            .map(|(&(a, b), l)| format!("{}-{}-{}", a, b, l.as_micros()))
+            .collect::<Vec<String>>();
+        latencies.sort();
+
+        let mut hasher = Sha512::new();
+        hasher.update(latencies.join(","));
+
+        format!("3-{}-{:X}", self.tradeoff, hasher.finalize())
+    }
+
+    fn gen_routes(
+        &self,
+        nodes: &HashMap<usize, HashSet<usize>>,
+        f: usize,
+        s: usize,
+    ) -> Option<HashMap<usize, HashSet<usize>>> {
+        let mean = match self.latencies.is_empty() {
+            true => Topology::DEFAULT_LATENCY,
+            false => self.latencies.values().sum::<Duration>() / self.latencies.len() as u32,
+        };
+        let penalty = (mean.as_micros() as f64 * self.tradeoff).round() as u64;
+        let latency = |a: usize, b: usize| self.get_latency(a, b).as_micros() as u64;
+
+        let mut routes = nodes
+            .keys()
+            .map(|&n| (n, HashSet::new()))
+            .collect::<HashMap<usize, HashSet<usize>>>();
+        routes.insert(s, nodes.get(&s)?.clone());
+        let from_source = shortest_latencies(nodes, s, latency);
+
+        for t in by_distance(nodes, s) {
+            if nodes[&s].contains(&t) {
+                continue;
+            }
+
+            // The latency of the fastest path from `s` to `t` over every edge.
+            let to_target = shortest_latencies(nodes, t, latency);
+            let mut through = HashMap::new();
+            for (&a, neighbours) in nodes {
+                for &b in neighbours {
+                    if let (Some(x), Some(y)) = (from_source.get(&a), to_target.get(&b)) {
+                        through.insert((a, b), x + latency(a, b) + y);
+                    }
+                }
+            }
+
+            let mut bounds = through.values().copied().collect::<Vec<u64>>();
+            bounds.push(u64::MAX);
+            bounds.sort_unstable();
+            bounds.dedup();
+
+            let within = |bound: u64| {
+                let allowed = nodes
+                    .iter()
+                    .map(|(&a, neighbours)| {
+                        let reachable = neighbours
+                            .iter()
+                            .copied()
+                            .filter(|&b| through.get(&(a, b)).is_some_and(|&l| l <= bound))
+                            .collect::<HashSet<usize>>();
+                        (a, reachable)
+                    })
+                    .collect::<HashMap<usize, HashSet<usize>>>();
+
+                let paths =
+                    FlowGraph::directed(&allowed).cheapest_disjoint_paths(s, t, f + 1, |a, b| {
+                        match routes[&a].contains(&b) {
+                            true => latency(a, b),
+                            false => latency(a, b) + penalty,
+                        }
+                    });
+
+                let slowest = paths
+                    .iter()
+                    .map(|p| p.windows(2).map(|hop| latency(hop[0], hop[1])).sum::<u64>())
+                    .max();
+
+                match paths.len() > f && slowest <= Some(bound) {
+                    true => Some(paths),
+                    false => None,
+                }
+            };
+
+            // The cheapest paths under a looser bound may be slower than those under a
+            // tighter one, so every bound is tried from the tightest up. The loosest
+            // one always holds, as it leaves the paths simply the cheapest ones.
+            let best = bounds.into_iter().find_map(within)?;
+
+            for path in best {
+                for hop in path.windows(2) {
+                    routes.get_mut(&hop[0]).unwrap().insert(hop[1]);
+                }
+            }
+        }
+
+        Some(routes)
+    }
+}
+
+/// The latency of the fastest path from `s` to every node it can reach, by Dijkstra.
+fn shortest_latencies(
+    nodes: &HashMap<usize, HashSet<usize>>,
+    s: usize,
+    latency: impl Fn(usize, usize) -> u64,
+) -> HashMap<usize, u64> {
+    let mut distance = HashMap::from([(s, 0)]);
+    let mut q = BinaryHeap::from([Reverse((0, s))]);
+
+    while let Some(Reverse((d, n))) = q.pop() {
+        if d > distance[&n] {
+            continue;
+        }
+
+        for &neigh in nodes.get(&n).unwrap() {
+            let through = d + latency(n, neigh);
+
+            if distance.get(&neigh).is_none_or(|&old| through < old) {
+                distance.insert(neigh, through);
+                q.push(Reverse((through, neigh)));
+            }
+        }
+    }
+
+    distance
+}
+
+/// Adds how many neighbours every node but `s` relays to in `routes` to `load`.
+pub(crate) fn add_load(
+    load: &mut HashMap<usize, usize>,
+    s: usize,
+    routes: &HashMap<usize, HashSet<usize>>,
+) {
+    for (&n, route) in routes {
+        if n != s {
+            *load.entry(n).or_default() += route.len();
+        }
+    }
+}
+
+/// Adds the cheapest `f + 1` disjoint paths to every node in turn to the routes,
+/// where `cost(a, b, relays)` is the cost of the edge from `a` to `b`, given the
+/// neighbours `a` relays to already.
+fn cheapest_routes(
+    nodes: &HashMap<usize, HashSet<usize>>,
+    f: usize,
+    s: usize,
+    cost: impl Fn(usize, usize, &HashSet<usize>) -> u64,
+) -> Option<HashMap<usize, HashSet<usize>>> {
+    let flowgraph = FlowGraph::directed(nodes);
+    let mut routes = nodes
+        .keys()
+        .map(|&n| (n, HashSet::new()))
+        .collect::<HashMap<usize, HashSet<usize>>>();
+
+    // The source sends to all of its neighbours anyway, and they trust what
+    // comes straight from it.
+    routes.insert(s, nodes.get(&s)?.clone());
+
+    for t in by_distance(nodes, s) {
+        if nodes[&s].contains(&t) {
+            continue;
+        }
+
+        let paths = flowgraph.cheapest_disjoint_paths(s, t, f + 1, |a, b| cost(a, b, &routes[&a]));
+
+        if paths.len() <= f {
+            return None;
+        }
+
+        for path in paths {
+            for hop in path.windows(2) {
+                routes.get_mut(&hop[0]).unwrap().insert(hop[1]);
+            }
+        }
+    }
+
+    Some(routes)
+}
+
+/// All nodes other than `s`, closest to `s` first, so that the paths towards far
+/// nodes can build on those towards the nodes on the way.
+fn by_distance(nodes: &HashMap<usize, HashSet<usize>>, s: usize) -> Vec<usize> {
+    let mut distance = HashMap::from([(s, 0)]);
+    let mut q = VecDeque::from([s]);
+
+    while let Some(n) = q.pop_front() {
+        for &neigh in nodes.get(&n).unwrap() {
+            if !distance.contains_key(&neigh) {
+                distance.insert(neigh, distance[&n] + 1);
+                q.push_back(neigh);
+            }
+        }
+    }
+
+    let mut order = nodes
+        .keys()
+        .copied()
+        .filter(|&n| n != s)
+        .collect::<Vec<usize>>();
+    order.sort_by_key(|n| (distance.get(n).copied().unwrap_or(usize::MAX), *n));
+
+    order
+}
+
+/// Finds the fewest route edges with which every node is reached over `f + 1`
+/// node-disjoint paths, by branch and bound over the directed edges.
+///
+/// This takes exponential time, so topologies of more than [`Optimal::MAX_NODES`]
+/// nodes are refused. Meant as a yardstick for the other strategies.
+#[derive(Debug, Default)]
+pub struct Optimal;
+
+impl Optimal {
+    /// The largest topology the exact search is attempted on.
+    pub const MAX_NODES: usize = 15;
+}
+
+impl RouteStrategy for Optimal {
+    fn get_name(&self) -> &str {
+        "optimal"
+    }
+
+    fn gen_routes(
+        &self,
+        nodes: &HashMap<usize, HashSet<usize>>,
+        f: usize,
+        s: usize,
+    ) -> Option<HashMap<usize, HashSet<usize>>> {
+        if nodes.len() > Self::MAX_NODES {
+            return None;
+        }
+
+        // The best heuristic routes bound the search from the start.
+        let incumbent = Suurballe::default().gen_routes(nodes, f, s)?;
+        let mut search = Search::new(nodes, f, s);
+        let best = search.solve(&incumbent);
+
+        Some(match best {
+            Some(routes) => routes,
+            None => incumbent,
+        })
+    }
+}
+
+/// The state of the branch and bound of [`Optimal`].
+///
+/// The source sends to all of its neighbours anyway, and those are reached by that
+/// copy alone, so only the edges between the other nodes are decided, one by one,
+/// and only nodes further away are targets that need `f + 1` paths.
+struct Search<'a> {
+    nodes: &'a HashMap<usize, HashSet<usize>>,
+    f: usize,
+    s: usize,
+    targets: Vec<usize>,
+    arcs: Vec<(usize, usize)>,
+    included: Vec<bool>,
+    indegree: HashMap<usize, usize>,
+    best: Option<(usize, Vec<bool>)>,
+}
+
+impl<'a> Search<'a> {
+    fn new(nodes: &'a HashMap<usize, HashSet<usize>>, f: usize, s: usize) -> Self {
+        let targets = by_distance(nodes, s)
+            .into_iter()
+            .filter(|t| !nodes[&s].contains(t))
+            .collect::<Vec<usize>>();
+
+        // Only edges towards targets can help, ordered like the targets so that
+        // the nodes close to the source are settled first.
+        let mut arcs = Vec::new();
+        for &t in &targets {
+            let mut tails = nodes[&t]
+                .iter()
+                .copied()
+                .filter(|&v| v != s)
+                .collect::<Vec<usize>>();
+            tails.sort_unstable();
+
+            arcs.extend(tails.into_iter().map(|v| (v, t)));
+        }
+
+        Search {
+            nodes,
+            f,
+            s,
+            indegree: targets.iter().map(|&t| (t, 0)).collect(),
+            targets,
+            included: vec![false; arcs.len()],
+            arcs,
+            best: None,
+        }
+    }
+
+    /// Searches for routes with fewer edges than `incumbent`, and returns the best.
+    fn solve(
+        &mut self,
+        incumbent: &HashMap<usize, HashSet<usize>>,
+    ) -> Option<HashMap<usize, HashSet<usize>>> {
+        let size = incumbent
+            .iter()
+            .filter(|&(&v, _)| v != self.s)
+            .map(|(_, r)| r.iter().filter(|w| self.indegree.contains_key(w)).count())
+            .sum::<usize>();
+        self.best = Some((size + 1, Vec::new()));
+
+        if !self.feasible(0) {
+            return None;
+        }
+
+        self.branch(0, 0);
+
+        let (_, included) = self.best.take()?;
+        if included.is_empty() {
+            return None;
+        }
+
+        Some(self.routes(&included, self.arcs.len()))
+    }
+
+    fn branch(&mut self, i: usize, size: usize) {
+        // Every target still needs `f + 1` edges into it.
+        let missing = self
+            .indegree
+            .values()
+            .map(|&d| (self.f + 1).saturating_sub(d))
+            .sum::<usize>();
+
+        if size + missing >= self.best.as_ref().map_or(usize::MAX, |b| b.0) {
+            return;
+        }
+
+        if missing == 0 && self.feasible(self.arcs.len()) {
+            self.best = Some((size, self.included.clone()));
+            return;
+        }
+
+        if i == self.arcs.len() {
+            return;
+        }
+
+        let head = self.arcs[i].1;
+        self.included[i] = true;
+        *self.indegree.get_mut(&head).unwrap() += 1;
+        self.branch(i + 1, size + 1);
+
+        self.included[i] = false;
+        *self.indegree.get_mut(&head).unwrap() -= 1;
+        if self.feasible(i + 1) {
+            self.branch(i + 1, size);
+        }
+    }
+
+    /// Whether every target can still get `f + 1` disjoint paths when all edges from
+    /// `undecided` on are added to the included ones.
+    fn feasible(&self, undecided: usize) -> bool {
+        let flowgraph = FlowGraph::directed(&self.routes(&self.included, undecided));
+
+        self.targets
+            .iter()
+            .all(|&t| flowgraph.max_vertex_flow(self.s, t) > self.f)
+    }
+
+    fn routes(&self, included: &[bool], undecided: usize) -> HashMap<usize, HashSet<usize>> {
+        let mut routes = self
+            .nodes
+            .keys()
+            .map(|&n| (n, HashSet::new()))
+            .collect::<HashMap<usize, HashSet<usize>>>();
+        routes.insert(self.s, self.nodes[&self.s].clone());
+
+        for (i, &(v, w)) in self.arcs.iter().enumerate() {
+            if i >= undecided || included[i] {
+                routes.get_mut(&v).unwrap().insert(w);
+            }
+        }
+
+        routes
+    }
+}
+
+/// The strategies added with [`register`].
+static REGISTERED: Mutex<Vec<Arc<dyn RouteStrategy>>> = Mutex::new(Vec::new());
+
+/// The strategies of this crate, other than [`Latency`], which depends on the
+/// latencies of one topology and so is made with [`Latency::new`] for it instead.
+fn built_in() -> Vec<Arc<dyn RouteStrategy>> {
+    vec![
+        Arc::new(Pathfind),
+        Arc::new(Unreliable),
+        Arc::new(Suurballe::default()),
+        Arc::new(MinCost),
+        Arc::new(Balanced),
+        Arc::new(Optimal),
+    ]
+}
+
+/// Every strategy of this crate but [`Latency`], followed by the registered ones.
+pub fn all() -> Vec<Arc<dyn RouteStrategy>> {
+    let mut all = built_in();
+    all.extend(REGISTERED.lock().unwrap().iter().cloned());
+
+    all
+}
+
+/// Makes a strategy built outside of this crate selectable by its name, like the
+/// strategies of this crate. Returns `false` if the name is taken already.
+pub fn register(strategy: Arc<dyn RouteStrategy>) -> bool {
+    // Checked under the same lock as the push, so a name is never taken twice.
+    let mut registered = REGISTERED.lock().unwrap();
+    let name = strategy.get_name();
+
+    if built_in()
+        .iter()
+        .chain(registered.iter())
+        .any(|s| s.get_name() == name)
+    {
+        return false;
+    }
+
+    registered.push(strategy);
+    true
+}
+
+/// The strategy with the given name.
+pub fn from_name(name: &str) -> Option<Arc<dyn RouteStrategy>> {
+    all().into_iter().find(|s| s.get_name() == name)
+}
+
+/// The names of all strategies, for listing the valid choices.
+pub fn get_names() -> Vec<String> {
+    all().iter().map(|s| s.get_name().to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Seed, Topology};
+
+    #[test]
+    fn registered_strategies_are_selectable() {
+        #[derive(Debug)]
+        struct Renamed;
+
+        impl RouteStrategy for Renamed {
+            fn get_name(&self) -> &str {
+                "renamed"
+            }
+
+            fn gen_routes(
+                &self,
+                nodes: &HashMap<usize, HashSet<usize>>,
+                f: usize,
+                s: usize,
+            ) -> Option<HashMap<usize, HashSet<usize>>> {
+                Pathfind.gen_routes(nodes, f, s)
+            }
+        }
+
+        assert!(from_name("renamed").is_none());
+        assert!(register(Arc::new(Renamed)));
+        assert_eq!(from_name("renamed").unwrap().get_name(), "renamed");
+        assert!(get_names().contains(&"renamed".to_string()));
+
+        assert!(!register(Arc::new(Renamed)));
+        assert!(!register(Arc::new(Pathfind)));
+    }
+
+    #[test]
+    fn cheapest_routes_survive_f_crashes() {
+        let mut topology = Topology::default();
+        assert!(topology.generate(12, 5, 0, Seed::new(3)));
+        let nodes = FlowGraph::new(&topology.get_edges()).get_nodes();
+
+        for s in [0, 5] {
+            for strategy in [&Suurballe::default() as &dyn RouteStrategy, &MinCost] {
+                let routes = strategy.gen_routes(&nodes, 2, s).unwrap();
+
+                // Neighbours of the source only need its own copy, and everyone else
+                // gets exactly the `f + 1` relays they need.
+                let sent = routes.values().map(|r| r.len()).sum::<usize>();
+                assert_eq!(sent, nodes[&s].len() + 3 * (11 - nodes[&s].len()));
+                assert_eq!(RouteCache::find_counterexample(&nodes, &routes, 2, s), None);
+            }
+        }
+    }
+
+    #[test]
+    fn optimal_search_improves_on_any_incumbent() {
+        // A ring with the chord 1 - 4.
+        let mut edges = (0..8).map(|a| (a, (a + 1) % 8)).collect::<Vec<_>>();
+        edges.push((1, 4));
+        let nodes = FlowGraph::new(&edges).get_nodes();
+
+        // Starting from routing over every edge, the search still ends up with
+        // the two edges into each of the five nodes not next to the source.
+        let routes = Search::new(&nodes, 1, 0).solve(&nodes).unwrap();
+        let sent = routes.values().map(|r| r.len()).sum::<usize>();
+        assert_eq!(sent, 2 + 2 * 5);
+        assert_eq!(RouteCache::find_counterexample(&nodes, &routes, 1, 0), None);
+
+        let routes = Optimal.gen_routes(&nodes, 1, 0).unwrap();
+        assert_eq!(routes.values().map(|r| r.len()).sum::<usize>(), sent);
+
+        let ring = (0..20).map(|a| (a, (a + 1) % 20)).collect::<Vec<_>>();
+        let nodes = FlowGraph::new(&ring).get_nodes();
+        assert!(Optimal.gen_routes(&nodes, 1, 0).is_none());
+    }
+
+    #[test]
+    fn latency_routes_avoid_slow_edges() {
+        // Towards 5, the fastest pair of disjoint paths, over 1 - 3 and 2 - 4, takes
+        // 11 and 100 ms, while the pair over 1 - 4 and 2 - 3 takes 60 ms each.
+        let latencies = [
+            ((0, 1), 5),
+            ((0, 2), 5),
+            ((1, 3), 1),
+            ((1, 4), 50),
+            ((2, 3), 50),
+            ((2, 4), 90),
+            ((3, 5), 5),
+            ((4, 5), 5),
+        ];
+        let edges = latencies.iter().map(|&(e, _)| e).collect::<Vec<_>>();
+        let nodes = FlowGraph::new(&edges).get_nodes();
+        let mut topology = Topology::from_edges(edges, Vec::new());
+        for ((a, b), latency) in latencies {
+            topology.set_latency(a, b, Duration::from_millis(latency));
+        }
+
+        let routes = Latency::new(&topology, 0.)
+            .gen_routes(&nodes, 1, 0)
+            .unwrap();
+
+        // The slowest of the two fastest node-disjoint paths to `t` in the routes.
+        let slowest = |t: usize| {
+            let mut paths = Vec::new();
+            let mut stack = vec![vec![0]];
+            while let Some(path) = stack.pop() {
+                let last = *path.last().unwrap();
+                if last == t {
+                    paths.push(path);
+                    continue;
+                }
+
+                for &next in &routes[&last] {
+                    if !path.contains(&next) {
+                        let mut longer = path.clone();
+                        longer.push(next);
+                        stack.push(longer);
+                    }
+                }
+            }
+
+            let latency = |p: &Vec<usize>| {
+                p.windows(2)
+                    .map(|hop| topology.get_latency(hop[0], hop[1]))
+                    .sum::<Duration>()
+            };
+
+            paths
+                .iter()
+                .flat_map(|p| paths.iter().map(move |q| (p, q)))
+                .filter(|(p, q)| p != q && p[1..p.len() - 1].iter().all(|v| !q.contains(v)))
+                .map(|(p, q)| latency(p).max(latency(q)))
+                .min()
+                .unwrap()
+        };
+
+        assert_eq!(slowest(3), Duration::from_millis(55));
+        assert_eq!(slowest(4), Duration::from_millis(65));
+        assert_eq!(slowest(5), Duration::from_millis(60));
+    }
+
+    #[test]
+    fn balanced_routes_spread_the_relaying() {
+        let edges = vec![
+            (0, 1),
+            (0, 5),
+            (0, 6),
+            (0, 8),
+            (0, 9),
+            (1, 2),
+            (1, 4),
+            (1, 5),
+            (1, 9),
+            (2, 7),
+            (2, 8),
+            (2, 10),
+            (2, 11),
+            (3, 6),
+            (3, 7),
+            (3, 9),
+            (3, 10),
+            (3, 11),
+            (4, 6),
+            (4, 9),
+            (4, 10),
+            (4, 11),
+            (5, 7),
+            (5, 10),
+            (5, 11),
+            (6, 7),
+            (6, 11),
+            (7, 8),
+            (8, 9),
+            (8, 10),
+        ];
+        let nodes = FlowGraph::new(&edges).get_nodes();
+
+        // The most any node other than the source relays to.
+        let busiest = |routes: &HashMap<usize, HashSet<usize>>, s: usize| {
+            routes
+                .iter()
+                .filter(|&(&n, _)| n != s)
+                .map(|(_, r)| r.len())
+                .max()
+                .unwrap()
+        };
+
+        for s in [0, 5] {
+            let balanced = Balanced.gen_routes(&nodes, 2, s).unwrap();
+            let mincost = MinCost.gen_routes(&nodes, 2, s).unwrap();
+
+            assert_eq!(
+                RouteCache::find_counterexample(&nodes, &balanced, 2, s),
+                None
+            );
+            assert!(busiest(&balanced, s) < busiest(&mincost, s));
+        }
+
+        // The most any node relays to, summed over the broadcasts of all sources.
+        let busiest_overall = |all: &HashMap<usize, Arc<HashMap<usize, HashSet<usize>>>>| {
+            let mut load = HashMap::<usize, usize>::new();
+            for (&s, routes) in all {
+                for (&n, route) in routes.iter() {
+                    if n != s {
+                        *load.entry(n).or_default() += route.len();
+                    }
+                }
+            }
+
+            load.into_values().max().unwrap()
+        };
+
+        let all = |strategy: Arc<dyn RouteStrategy>| {
+            RouteCache::new(strategy).gen_all_routes(&nodes, 2).unwrap()
+        };
+        let separately = nodes
+            .keys()
+            .map(|&s| (s, Arc::new(Balanced.gen_routes(&nodes, 2, s).unwrap())))
+            .collect::<HashMap<_, _>>();
+        let carried = all(Arc::new(Balanced));
+
+        for (&s, routes) in &carried {
+            assert_eq!(RouteCache::find_counterexample(&nodes, routes, 2, s), None);
+        }
+        assert!(busiest_overall(&carried) < busiest_overall(&separately));
+        assert!(busiest_overall(&carried) < busiest_overall(&all(Arc::new(MinCost))));
+    }
+}
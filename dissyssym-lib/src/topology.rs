@@ -1,25 +1,73 @@
 use std::{
-    collections::{HashMap, HashSet, VecDeque},
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap, HashMap, HashSet, VecDeque},
     io::Write,
-    path::Path,
+    path::{Path, PathBuf},
+    time::Duration,
 };
 
 use rand::{
-    prelude::{IteratorRandom, SliceRandom, ThreadRng},
-    thread_rng,
+    prelude::{IteratorRandom, SliceRandom},
+    rngs::StdRng,
 };
 use tokio::fs::read_to_string;
 
-#[derive(Debug, Clone)]
+use crate::{RouteCache, Seed};
+
+/// How the faulty nodes of a topology are placed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    /// Uniformly at random, which may include the sender.
+    Random,
+    /// On a minimum vertex cut, topped up with the highest-degree nodes.
+    MinCut,
+    /// On the nodes with the most neighbours.
+    HighestDegree,
+    /// On the nodes that relay the broadcasts of the sender to the most neighbours.
+    MostRoutes,
+    /// One by one on the node that leaves the most correct nodes without the
+    /// broadcast of the sender.
+    Greedy,
+}
+
+impl Placement {
+    pub const ALL: [Placement; 5] = [
+        Placement::Random,
+        Placement::MinCut,
+        Placement::HighestDegree,
+        Placement::MostRoutes,
+        Placement::Greedy,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.get_name() == name)
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Placement::Random => "random",
+            Placement::MinCut => "mincut",
+            Placement::HighestDegree => "degree",
+            Placement::MostRoutes => "routes",
+            Placement::Greedy => "greedy",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct Topology {
     n: usize,
     c: usize,
     edges: Vec<(usize, usize)>,
     faulty: Vec<usize>,
+    latencies: HashMap<(usize, usize), Duration>,
 }
 
 impl Topology {
-    pub fn generate(&mut self, n: usize, c: usize, f: usize) -> bool {
+    /// The expected delay of an edge without a latency of its own.
+    pub const DEFAULT_LATENCY: Duration = Duration::from_millis(75);
+
+    pub fn generate(&mut self, n: usize, c: usize, f: usize, seed: Seed) -> bool {
         assert!(c > 0, "Connectivity must be at least one.");
         assert!(n > c, "Connectivity has to be lower than total.");
 
@@ -29,7 +77,7 @@ impl Topology {
 
         self.n = n;
         self.c = c;
-        let mut rng = thread_rng();
+        let mut rng = seed.rng("topology");
         let mut attempt = 0;
 
         // Try to create a graph until it's valid.
@@ -45,7 +93,8 @@ impl Topology {
                 None => continue,
             };
 
-            if Self::connectivity(&edges, n) != c {
+            // The graph is `c`-regular, so it is at most `c` connected.
+            if !Self::is_vertex_connected(&edges, n, c) {
                 continue;
             }
 
@@ -53,15 +102,20 @@ impl Topology {
         };
 
         // Set some random nodes as faulty.
-        self.faulty = (0..n).choose_multiple(&mut rng, f.try_into().unwrap());
+        self.faulty = (0..n).choose_multiple(&mut seed.rng("faulty"), f);
         true
     }
 
     pub fn write(&self, path: impl AsRef<Path>) {
         let mut result = String::new();
 
-        for (n1, n2) in &self.edges {
-            result.push_str(format!("{} {}\n", n1, n2).as_str());
+        for &(n1, n2) in &self.edges {
+            match self.latencies.get(&(n1.min(n2), n1.max(n2))) {
+                Some(latency) => {
+                    result.push_str(format!("{} {} {}\n", n1, n2, latency.as_millis()).as_str())
+                }
+                None => result.push_str(format!("{} {}\n", n1, n2).as_str()),
+            }
         }
 
         std::fs::File::create(path)
@@ -70,12 +124,37 @@ impl Topology {
             .expect("Failed to write content of the topology.")
     }
 
-    pub async fn parse(path: impl AsRef<Path>, f: usize) -> Option<Self> {
+    /// The topology files in `dir` of at most `max_n` nodes, sorted by name. Files are
+    /// named `<n>-<c>-<i>.tpgy`, as `gentopology` writes them.
+    pub fn list(dir: impl AsRef<Path>, max_n: usize) -> Vec<PathBuf> {
+        let mut entries = std::fs::read_dir(dir)
+            .expect("Failed to read topologies dir.")
+            .map(|res| res.unwrap().path())
+            .filter(|p| {
+                let file = p.file_name().unwrap().to_str().unwrap();
+                let n = file.split('-').next().unwrap().parse::<usize>().unwrap();
+                n <= max_n
+            })
+            .collect::<Vec<PathBuf>>();
+        entries.sort();
+
+        entries
+    }
+
+    /// The index `i` of a topology file named `<n>-<c>-<i>.tpgy`, which tells apart
+    /// the topologies generated for the same `n` and `c`.
+    pub fn file_index(path: &Path) -> usize {
+        let file = path.file_stem().unwrap().to_str().unwrap();
+        file.split('-').nth(2).unwrap().parse::<usize>().unwrap()
+    }
+
+    pub async fn parse(path: impl AsRef<Path>, f: usize, seed: Seed) -> Option<Self> {
         let content = read_to_string(&path)
             .await
             .expect("Failed to read topology file!");
         let mut uniques = HashSet::new();
         let mut edges = Vec::new();
+        let mut latencies = HashMap::new();
 
         for line in content.lines() {
             let mut split = line.split(' ');
@@ -92,32 +171,150 @@ impl Topology {
 
             uniques.insert(a);
             uniques.insert(b);
-
-            if a < b {
-                edges.push((a, b));
-            } else {
-                edges.push((b, a));
+            let edge = (a.min(b), a.max(b));
+            edges.push(edge);
+
+            // The expected latency in milliseconds is optional.
+            if let Some(latency) = split.next() {
+                let latency = latency
+                    .parse::<u64>()
+                    .expect("Failed to parse latency as u64.");
+                latencies.insert(edge, Duration::from_millis(latency));
             }
         }
 
         let n = uniques.len();
-        let c = Self::connectivity(&edges, n);
+        let c = Self::vertex_connectivity(&edges, n);
 
         if c <= f {
             return None;
         }
 
-        let mut rng = thread_rng();
-        let faulty = (0..n).choose_multiple(&mut rng, f);
+        let faulty = (0..n).choose_multiple(&mut seed.rng("faulty"), f);
 
         Some(Self {
             n,
             c,
             edges,
             faulty,
+            latencies,
         })
     }
 
+    /// Replaces the faulty nodes by `f` nodes placed according to `placement`.
+    /// Except for random placement, the `sender` is never made faulty, and the
+    /// routes are those `route_cache` generates for it.
+    ///
+    /// Returns `false` if no routes could be generated for the sender.
+    pub fn place_faulty(
+        &mut self,
+        f: usize,
+        placement: Placement,
+        sender: usize,
+        seed: Seed,
+        route_cache: &RouteCache,
+    ) -> bool {
+        let nodes = FlowGraph::new(&self.edges).get_nodes();
+        let by_degree = {
+            let mut by_degree = (0..self.n).filter(|&v| v != sender).collect::<Vec<usize>>();
+            by_degree.sort_by_key(|v| (Reverse(nodes[v].len()), *v));
+            by_degree
+        };
+
+        self.faulty = match placement {
+            Placement::Random => (0..self.n).choose_multiple(&mut seed.rng("faulty"), f),
+            Placement::HighestDegree => by_degree.into_iter().take(f).collect(),
+            Placement::MinCut => {
+                let mut cut = Self::min_vertex_cut(&self.edges, self.n).unwrap_or_default();
+                cut.retain(|&v| v != sender);
+                cut.sort_unstable();
+
+                cut.into_iter()
+                    .chain(by_degree)
+                    .fold(Vec::new(), |mut faulty, v| {
+                        if faulty.len() < f && !faulty.contains(&v) {
+                            faulty.push(v);
+                        }
+
+                        faulty
+                    })
+            }
+            Placement::MostRoutes | Placement::Greedy => {
+                let routes = match route_cache.gen_routes(&nodes, f, sender) {
+                    Some(routes) => routes,
+                    None => return false,
+                };
+
+                let mut by_routes = by_degree;
+                by_routes.sort_by_key(|v| Reverse(routes[v].len()));
+
+                match placement {
+                    Placement::Greedy => Self::place_greedy(&nodes, &routes, f, sender, by_routes),
+                    _ => by_routes.into_iter().take(f).collect(),
+                }
+            }
+        };
+
+        true
+    }
+
+    /// Adds the faulty node that leaves the most correct nodes without a delivery,
+    /// until there are `f`. Ties go to the earliest node in `candidates`.
+    fn place_greedy(
+        nodes: &HashMap<usize, HashSet<usize>>,
+        routes: &HashMap<usize, HashSet<usize>>,
+        f: usize,
+        sender: usize,
+        mut candidates: Vec<usize>,
+    ) -> Vec<usize> {
+        let mut faulty = HashSet::new();
+        let mut placed = Vec::new();
+
+        while placed.len() < f && !candidates.is_empty() {
+            let mut best = (0, 0);
+
+            for (i, &v) in candidates.iter().enumerate() {
+                faulty.insert(v);
+                let undelivered = RouteCache::undelivered(nodes, routes, sender, &faulty).len();
+                faulty.remove(&v);
+
+                if undelivered > best.1 {
+                    best = (i, undelivered);
+                }
+            }
+
+            let v = candidates.remove(best.0);
+            faulty.insert(v);
+            placed.push(v);
+        }
+
+        placed
+    }
+
+    /// Builds a topology from known edges and faulty nodes, without any checks.
+    pub fn from_edges(edges: Vec<(usize, usize)>, faulty: Vec<usize>) -> Self {
+        let n = FlowGraph::new(&edges).get_nodes().len();
+        let c = Self::vertex_connectivity(&edges, n);
+
+        Self {
+            n,
+            c,
+            edges,
+            faulty,
+            latencies: HashMap::new(),
+        }
+    }
+
+    /// The complete graph on `n` nodes, with the given faulty nodes.
+    #[cfg(test)]
+    pub(crate) fn complete(n: usize, faulty: Vec<usize>) -> Self {
+        let edges = (0..n)
+            .flat_map(|a| ((a + 1)..n).map(move |b| (a, b)))
+            .collect();
+
+        Self::from_edges(edges, faulty)
+    }
+
     pub fn get_edges(&self) -> Vec<(usize, usize)> {
         self.edges.clone()
     }
@@ -126,23 +323,54 @@ impl Topology {
         self.n
     }
 
+    /// The neighbours of node `n`, in ascending order.
+    pub fn get_neighbours(&self, n: usize) -> Vec<usize> {
+        let mut neighbours = self
+            .edges
+            .iter()
+            .filter_map(|&(a, b)| match (a == n, b == n) {
+                (true, _) => Some(b),
+                (_, true) => Some(a),
+                _ => None,
+            })
+            .collect::<Vec<usize>>();
+        neighbours.sort_unstable();
+        neighbours.dedup();
+
+        neighbours
+    }
+
     pub fn get_faulty(&self) -> Vec<usize> {
         self.faulty.clone()
     }
 
+    /// The expected delay of the edge between `a` and `b`.
+    pub fn get_latency(&self, a: usize, b: usize) -> Duration {
+        self.latencies
+            .get(&(a.min(b), a.max(b)))
+            .copied()
+            .unwrap_or(Self::DEFAULT_LATENCY)
+    }
+
+    /// Sets the expected delay of the edge between `a` and `b`, around which the
+    /// delays of its messages are sampled.
+    pub fn set_latency(&mut self, a: usize, b: usize, latency: Duration) {
+        self.latencies.insert((a.min(b), a.max(b)), latency);
+    }
+
     pub fn get_c(&self) -> usize {
         self.c
     }
 
-    fn try_generate(rng: &mut ThreadRng, n: usize, d: usize) -> Option<Vec<(usize, usize)>> {
+    fn try_generate(rng: &mut StdRng, n: usize, d: usize) -> Option<Vec<(usize, usize)>> {
         let mut edges = Vec::new();
         let mut stubs: Vec<usize> = (0..d)
-            .map(|_| (0..n).collect::<Vec<usize>>())
-            .flatten()
+            .flat_map(|_| (0..n).collect::<Vec<usize>>())
             .collect::<_>();
 
         while !stubs.is_empty() {
-            let mut possible_edges = HashMap::new();
+            // Ordered, so that the stubs left over are shuffled the same way every run.
+            let mut possible_edges = BTreeMap::new();
             stubs.shuffle(rng);
 
             for i in 0..(stubs.len() / 2) {
@@ -150,9 +378,7 @@ impl Topology {
                 let mut s2 = *stubs.get((i * 2) + 1).unwrap();
 
                 if s1 > s2 {
-                    let tmp = s1;
-                    s1 = s2;
-                    s2 = tmp;
+                    std::mem::swap(&mut s1, &mut s2);
                 }
 
                 if s1 != s2 && !edges.contains(&(s1, s2)) {
@@ -178,13 +404,13 @@ impl Topology {
         Some(edges)
     }
 
-    fn suitable_graph(edges: &Vec<(usize, usize)>, possible_edges: &HashMap<usize, usize>) -> bool {
+    fn suitable_graph(edges: &[(usize, usize)], possible_edges: &BTreeMap<usize, usize>) -> bool {
         if possible_edges.is_empty() {
             return true;
         }
 
-        for (s1, _) in possible_edges {
-            for (s2, _) in possible_edges {
+        for s1 in possible_edges.keys() {
+            for s2 in possible_edges.keys() {
                 if s1 == s2 {
                     continue;
                 }
@@ -208,7 +434,97 @@ impl Topology {
         false
     }
 
-    pub fn connectivity(edges: &Vec<(usize, usize)>, n: usize) -> usize {
+    /// The least number of nodes whose removal disconnects the graph, or `n - 1`
+    /// for a complete graph. This is the `c` the bounds on `f` refer to.
+    pub fn vertex_connectivity(edges: &Vec<(usize, usize)>, n: usize) -> usize {
+        Self::bounded_vertex_connectivity(edges, n, None).0
+    }
+
+    /// Whether at least `k` nodes have to be removed to disconnect the graph.
+    /// Stops as soon as a smaller separator is found.
+    pub fn is_vertex_connected(edges: &Vec<(usize, usize)>, n: usize, k: usize) -> bool {
+        Self::bounded_vertex_connectivity(edges, n, Some(k)).0 >= k
+    }
+
+    /// A smallest set of nodes whose removal disconnects the graph, empty if it
+    /// is disconnected already. `None` for a complete graph, which has no such set.
+    pub fn min_vertex_cut(edges: &Vec<(usize, usize)>, n: usize) -> Option<Vec<usize>> {
+        if n < 2 {
+            return None;
+        }
+
+        let flowgraph = FlowGraph::new(edges);
+        let nodes = flowgraph.get_nodes();
+
+        if (0..n).any(|v| !nodes.contains_key(&v)) {
+            return Some(Vec::new());
+        }
+
+        let (_, (s, t)) = Self::bounded_vertex_connectivity(edges, n, None);
+        flowgraph.min_vertex_cut(s?, t?)
+    }
+
+    /// Esfahanian–Hakimi: every minimum separator either misses a node `v` of
+    /// minimum degree, or contains it and then separates two of its neighbours.
+    /// So only flows from `v` and between neighbours of `v` are needed.
+    ///
+    /// With a `target`, flows are cut off at it and the search ends once the
+    /// connectivity is known to be lower. Also returns a pair of nodes that is
+    /// separated by a smallest separator, if there is any.
+    fn bounded_vertex_connectivity(
+        edges: &Vec<(usize, usize)>,
+        n: usize,
+        target: Option<usize>,
+    ) -> (usize, (Option<usize>, Option<usize>)) {
+        let nodes = FlowGraph::new(edges).get_nodes();
+
+        // Some node has no edges at all.
+        if n < 2 || (0..n).any(|v| !nodes.contains_key(&v)) {
+            return (0, (None, None));
+        }
+
+        let v = (0..n).min_by_key(|v| nodes[v].len()).unwrap();
+        let mut neighbours = nodes[&v].iter().copied().collect::<Vec<usize>>();
+        neighbours.sort_unstable();
+
+        let mut pairs = (0..n)
+            .filter(|u| *u != v && !nodes[&v].contains(u))
+            .map(|u| (v, u))
+            .collect::<Vec<(usize, usize)>>();
+
+        for (i, &x) in neighbours.iter().enumerate() {
+            for &y in &neighbours[(i + 1)..] {
+                if !nodes[&x].contains(&y) {
+                    pairs.push((x, y));
+                }
+            }
+        }
+
+        // The neighbours of `v` separate it from any other node, so the first pair
+        // is separated by at most `min` nodes until a better one is found.
+        let mut network = SplitNetwork::new(&nodes);
+        let mut min = neighbours.len();
+        let mut separated = pairs.first().copied();
+
+        for (s, t) in pairs {
+            let limit = target.map_or(min, |k| k.min(min));
+            let flow = network.max_flow(s, t, limit);
+
+            if flow < min {
+                min = flow;
+                separated = Some((s, t));
+            }
+
+            if target.is_some_and(|k| min < k) || min == 0 {
+                break;
+            }
+        }
+
+        (min, separated.unzip())
+    }
+
+    /// The least number of edges whose removal disconnects the graph.
+    pub fn edge_connectivity(edges: &Vec<(usize, usize)>, n: usize) -> usize {
         let flowgraph = FlowGraph::new(edges);
         let mut min = n;
 
@@ -218,7 +534,7 @@ impl Topology {
 
         for i in 0..n {
             for j in (i + 1)..n {
-                let max = flowgraph.max_flow(i, j);
+                let max = flowgraph.max_edge_flow(i, j);
                 min = min.min(max);
             }
         }
@@ -227,17 +543,6 @@ impl Topology {
     }
 }
 
-impl Default for Topology {
-    fn default() -> Self {
-        Self {
-            n: 0,
-            c: 0,
-            edges: Vec::new(),
-            faulty: Vec::new(),
-        }
-    }
-}
-
 #[derive(Debug, Clone)]
 pub struct FlowGraph {
     nodes: HashMap<usize, HashSet<usize>>,
@@ -248,13 +553,13 @@ impl FlowGraph {
         let mut nodes: HashMap<usize, HashSet<usize>> = HashMap::with_capacity(edges.len());
 
         for (a, b) in edges {
-            if let Some(node) = nodes.get_mut(&a) {
+            if let Some(node) = nodes.get_mut(a) {
                 node.insert(*b);
             } else {
                 nodes.insert(*a, HashSet::from([*b]));
             }
 
-            if let Some(node) = nodes.get_mut(&b) {
+            if let Some(node) = nodes.get_mut(b) {
                 node.insert(*a);
             } else {
                 nodes.insert(*b, HashSet::from([*a]));
@@ -264,11 +569,85 @@ impl FlowGraph {
         FlowGraph { nodes }
     }
 
+    /// Builds a directed flow graph, with an arc from every node to each of its `arcs`.
+    pub fn directed(arcs: &HashMap<usize, HashSet<usize>>) -> Self {
+        let mut nodes = arcs.clone();
+
+        for heads in arcs.values() {
+            for &head in heads {
+                nodes.entry(head).or_default();
+            }
+        }
+
+        FlowGraph { nodes }
+    }
+
     pub fn get_nodes(&self) -> HashMap<usize, HashSet<usize>> {
         self.nodes.clone()
     }
 
-    pub fn max_flow(&self, s: usize, t: usize) -> usize {
+    /// The number of internally node-disjoint paths between `s` and `t`.
+    pub fn max_vertex_flow(&self, s: usize, t: usize) -> usize {
+        if s == t || !self.nodes.contains_key(&s) || !self.nodes.contains_key(&t) {
+            return 0;
+        }
+
+        SplitNetwork::new(&self.nodes).max_flow(s, t, usize::MAX)
+    }
+
+    /// A smallest set of nodes, other than `s` and `t`, whose removal disconnects
+    /// them. `None` if they are adjacent, as no such set exists then.
+    pub fn min_vertex_cut(&self, s: usize, t: usize) -> Option<Vec<usize>> {
+        if s == t || !self.nodes.contains_key(&s) || !self.nodes.contains_key(&t) {
+            return None;
+        }
+
+        if self.nodes[&s].contains(&t) {
+            return None;
+        }
+
+        let mut network = SplitNetwork::new(&self.nodes);
+        network.max_flow(s, t, usize::MAX);
+
+        Some(network.min_cut(s))
+    }
+
+    /// A largest set of internally node-disjoint paths from `s` to `t`, which
+    /// witnesses their vertex connectivity. Every path starts with `s` and ends with `t`.
+    pub fn disjoint_paths(&self, s: usize, t: usize) -> Vec<Vec<usize>> {
+        if s == t || !self.nodes.contains_key(&s) || !self.nodes.contains_key(&t) {
+            return Vec::new();
+        }
+
+        let mut network = SplitNetwork::new(&self.nodes);
+        network.max_flow(s, t, usize::MAX);
+
+        network.paths(s, t)
+    }
+
+    /// Up to `k` internally node-disjoint paths from `s` to `t` of least total cost,
+    /// where using the edge from `a` to `b` costs `cost(a, b)`. These are found by
+    /// successive shortest paths on the node-split graph, as in Suurballe's and
+    /// Bhandari's algorithms.
+    pub fn cheapest_disjoint_paths(
+        &self,
+        s: usize,
+        t: usize,
+        k: usize,
+        cost: impl Fn(usize, usize) -> u64,
+    ) -> Vec<Vec<usize>> {
+        if s == t || !self.nodes.contains_key(&s) || !self.nodes.contains_key(&t) {
+            return Vec::new();
+        }
+
+        let mut network = SplitNetwork::new(&self.nodes);
+        network.min_cost_flow(s, t, k, &cost);
+
+        network.paths(s, t)
+    }
+
+    /// The number of edge-disjoint paths between `s` and `t`.
+    pub fn max_edge_flow(&self, s: usize, t: usize) -> usize {
         if !self.nodes.contains_key(&s) {
             return 0;
         }
@@ -304,9 +683,7 @@ impl FlowGraph {
 
                     q.push_back(neigh);
 
-                    if !pred.contains_key(&neigh) {
-                        pred.insert(neigh, n);
-                    }
+                    pred.entry(neigh).or_insert(n);
                 }
             }
 
@@ -343,3 +720,486 @@ impl FlowGraph {
         flow
     }
 }
+
+/// A flow network in which every node is split into an in-node and an out-node
+/// joined by an arc of capacity one, so that each node carries at most one path.
+/// The arcs of the edges are unbounded, so minimum cuts only consist of nodes.
+///
+/// Node `i` (by position in `labels`) becomes in-node `2i` and out-node `2i + 1`.
+/// Arcs are stored in pairs, so the reverse of arc `a` is `a ^ 1`.
+struct SplitNetwork {
+    labels: Vec<usize>,
+    index: HashMap<usize, usize>,
+    head: Vec<usize>,
+    full: Vec<u32>,
+    /// The capacities the last flow started from.
+    empty: Vec<u32>,
+    capacity: Vec<u32>,
+    outgoing: Vec<Vec<usize>>,
+}
+
+impl SplitNetwork {
+    fn new(nodes: &HashMap<usize, HashSet<usize>>) -> Self {
+        let mut labels = nodes.keys().copied().collect::<Vec<usize>>();
+        labels.sort_unstable();
+        let index = labels
+            .iter()
+            .enumerate()
+            .map(|(i, &l)| (l, i))
+            .collect::<HashMap<usize, usize>>();
+
+        let mut network = SplitNetwork {
+            head: Vec::new(),
+            full: Vec::new(),
+            empty: Vec::new(),
+            capacity: Vec::new(),
+            outgoing: vec![Vec::new(); 2 * labels.len()],
+            labels,
+            index,
+        };
+        let unbounded = network.labels.len() as u32;
+
+        for i in 0..network.labels.len() {
+            network.add_arc(2 * i, 2 * i + 1, 1);
+        }
+
+        for i in 0..network.labels.len() {
+            let mut neighbours = nodes[&network.labels[i]]
+                .iter()
+                .map(|b| network.index[b])
+                .collect::<Vec<usize>>();
+            neighbours.sort_unstable();
+
+            for j in neighbours {
+                network.add_arc(2 * i + 1, 2 * j, unbounded);
+            }
+        }
+
+        network
+    }
+
+    fn add_arc(&mut self, from: usize, to: usize, capacity: u32) {
+        self.outgoing[from].push(self.head.len());
+        self.head.push(to);
+        self.full.push(capacity);
+
+        self.outgoing[to].push(self.head.len());
+        self.head.push(from);
+        self.full.push(0);
+    }
+
+    /// Pushes up to `limit` paths from the out-node of `s` to the in-node of `t`,
+    /// starting from an empty flow.
+    fn max_flow(&mut self, s: usize, t: usize, limit: usize) -> usize {
+        let (source, sink) = self.reset(s, t);
+        let mut flow = 0;
+
+        while flow < limit && self.augment(source, sink) {
+            flow += 1;
+        }
+
+        flow
+    }
+
+    /// Pushes up to `k` paths from `s` to `t` of least total cost, where an arc
+    /// between the nodes `a` and `b` costs `cost(a, b)`. Every round sends one unit
+    /// over a cheapest path in the residual network, found by Dijkstra with
+    /// potentials, so that arcs of negative cost never have to be considered.
+    fn min_cost_flow(
+        &mut self,
+        s: usize,
+        t: usize,
+        k: usize,
+        cost: &dyn Fn(usize, usize) -> u64,
+    ) -> usize {
+        let (source, sink) = self.reset(s, t);
+        let costs = (0..self.head.len())
+            .map(|arc| {
+                // Reverse arcs undo the flow of their forward arc, and refund its cost.
+                let (from, to) = (self.head[arc | 1], self.head[arc & !1]);
+                let forward = match from / 2 == to / 2 {
+                    true => 0,
+                    false => cost(self.labels[from / 2], self.labels[to / 2]) as i64,
+                };
+
+                match arc % 2 {
+                    0 => forward,
+                    _ => -forward,
+                }
+            })
+            .collect::<Vec<i64>>();
+
+        let mut potential = vec![0; self.outgoing.len()];
+        let mut flow = 0;
+
+        while flow < k {
+            let mut dist = vec![i64::MAX; self.outgoing.len()];
+            let mut pred = vec![usize::MAX; self.outgoing.len()];
+            let mut heap = BinaryHeap::from([Reverse((0, source))]);
+            dist[source] = 0;
+
+            while let Some(Reverse((d, v))) = heap.pop() {
+                if d > dist[v] {
+                    continue;
+                }
+
+                for &arc in &self.outgoing[v] {
+                    let to = self.head[arc];
+                    let reduced = costs[arc] + potential[v] - potential[to];
+
+                    if self.capacity[arc] == 0 || d + reduced >= dist[to] {
+                        continue;
+                    }
+
+                    dist[to] = d + reduced;
+                    pred[to] = arc;
+                    heap.push(Reverse((dist[to], to)));
+                }
+            }
+
+            if dist[sink] == i64::MAX {
+                break;
+            }
+
+            // Capping at the distance of the sink keeps every reduced cost positive.
+            for (p, d) in potential.iter_mut().zip(&dist) {
+                *p += (*d).min(dist[sink]);
+            }
+
+            let mut v = sink;
+            while v != source {
+                let arc = pred[v];
+                self.capacity[arc] -= 1;
+                self.capacity[arc ^ 1] += 1;
+                v = self.head[arc ^ 1];
+            }
+
+            flow += 1;
+        }
+
+        flow
+    }
+
+    /// Empties the flow, and returns the network nodes between which paths from
+    /// `s` to `t` run.
+    fn reset(&mut self, s: usize, t: usize) -> (usize, usize) {
+        self.capacity = self.full.clone();
+
+        let source = 2 * self.index[&s] + 1;
+        let sink = 2 * self.index[&t];
+
+        // An edge between `s` and `t` themselves is a single path.
+        if let Some(&arc) = self.outgoing[source]
+            .iter()
+            .find(|&&a| self.head[a] == sink)
+        {
+            self.capacity[arc] = 1;
+        }
+
+        self.empty = self.capacity.clone();
+        (source, sink)
+    }
+
+    /// Finds a shortest path in the residual network and sends one unit over it.
+    fn augment(&mut self, source: usize, sink: usize) -> bool {
+        let pred = self.search(source, Some(sink));
+
+        if pred[sink] == usize::MAX {
+            return false;
+        }
+
+        let mut v = sink;
+        while v != source {
+            let arc = pred[v];
+            self.capacity[arc] -= 1;
+            self.capacity[arc ^ 1] += 1;
+            v = self.head[arc ^ 1];
+        }
+
+        true
+    }
+
+    /// Breadth-first search over the arcs with capacity left, returning for every
+    /// network node the arc it was reached by, or `usize::MAX` if it was not.
+    fn search(&self, source: usize, sink: Option<usize>) -> Vec<usize> {
+        let mut pred = vec![usize::MAX; self.outgoing.len()];
+        let mut q = VecDeque::from([source]);
+        pred[source] = usize::MAX - 1;
+
+        while let Some(v) = q.pop_front() {
+            if Some(v) == sink {
+                break;
+            }
+
+            for &arc in &self.outgoing[v] {
+                let to = self.head[arc];
+
+                if self.capacity[arc] == 0 || pred[to] != usize::MAX {
+                    continue;
+                }
+
+                pred[to] = arc;
+                q.push_back(to);
+            }
+        }
+
+        pred
+    }
+
+    /// After a maximum flow from `s`, the nodes whose in-node is still reachable
+    /// but whose out-node is not: their arcs are the saturated minimum cut.
+    fn min_cut(&self, s: usize) -> Vec<usize> {
+        let pred = self.search(2 * self.index[&s] + 1, None);
+
+        (0..self.labels.len())
+            .filter(|&i| pred[2 * i] != usize::MAX && pred[2 * i + 1] == usize::MAX)
+            .map(|i| self.labels[i])
+            .filter(|&l| l != s)
+            .collect()
+    }
+
+    /// Splits the current flow from `s` to `t` into its paths, consuming it.
+    fn paths(&mut self, s: usize, t: usize) -> Vec<Vec<usize>> {
+        let source = 2 * self.index[&s] + 1;
+        let sink = 2 * self.index[&t];
+        let mut paths = Vec::new();
+
+        // A forward arc carries flow when part of the capacity it started with is
+        // used, which for a direct arc between `s` and `t` is a single unit.
+        let carrying = |network: &Self, v: usize| {
+            network.outgoing[v]
+                .iter()
+                .copied()
+                .find(|&arc| network.capacity[arc] < network.empty[arc])
+        };
+
+        while let Some(mut arc) = carrying(self, source) {
+            let mut path = vec![s];
+
+            loop {
+                self.capacity[arc] += 1;
+                let v = self.head[arc];
+
+                if v & 1 == 0 {
+                    path.push(self.labels[v / 2]);
+                }
+
+                if v == sink {
+                    break;
+                }
+
+                arc = carrying(self, v).expect("Flow is conserved in every node.");
+            }
+
+            paths.push(path);
+        }
+
+        paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::strategy::Pathfind;
+
+    #[test]
+    fn vertex_and_edge_connectivity_differ() {
+        // Two triangles sharing node 2: two edges must go, but one node is enough.
+        let edges = vec![(0, 1), (0, 2), (1, 2), (2, 3), (2, 4), (3, 4)];
+
+        assert_eq!(Topology::vertex_connectivity(&edges, 5), 1);
+        assert_eq!(Topology::edge_connectivity(&edges, 5), 2);
+        assert_eq!(FlowGraph::new(&edges).max_vertex_flow(0, 3), 1);
+        assert_eq!(FlowGraph::new(&edges).max_edge_flow(0, 3), 2);
+    }
+
+    #[test]
+    fn complete_graph_vertex_connectivity() {
+        let edges = Topology::complete(5, Vec::new()).get_edges();
+
+        assert_eq!(Topology::vertex_connectivity(&edges, 5), 4);
+        assert_eq!(FlowGraph::new(&edges).max_vertex_flow(0, 1), 4);
+    }
+
+    fn all_pairs_connectivity(edges: &Vec<(usize, usize)>, n: usize) -> usize {
+        let flowgraph = FlowGraph::new(edges);
+        let nodes = flowgraph.get_nodes();
+        let mut min = n - 1;
+
+        for a in 0..n {
+            for b in (a + 1)..n {
+                if !nodes[&a].contains(&b) {
+                    min = min.min(flowgraph.max_vertex_flow(a, b));
+                }
+            }
+        }
+
+        min
+    }
+
+    #[test]
+    fn fast_vertex_connectivity_matches_all_pairs() {
+        let mut checked = 0;
+
+        for i in 0..20 {
+            let mut topology = Topology::default();
+            let (n, c) = (12 + i % 3 * 2, 2 + i % 4);
+
+            if !topology.generate(n, c, 0, Seed::new(i as u64)) {
+                continue;
+            }
+
+            // Without one of its edges the graph is no longer `c`-connected.
+            let edges = topology.get_edges();
+            let fewer = edges[1..].to_vec();
+
+            assert_eq!(
+                Topology::vertex_connectivity(&edges, n),
+                all_pairs_connectivity(&edges, n)
+            );
+            assert_eq!(
+                Topology::vertex_connectivity(&fewer, n),
+                all_pairs_connectivity(&fewer, n)
+            );
+            assert!(Topology::is_vertex_connected(&edges, n, c));
+            assert!(!Topology::is_vertex_connected(&fewer, n, c));
+            checked += 1;
+        }
+
+        assert!(checked > 10);
+    }
+
+    #[test]
+    fn adversarial_placements_avoid_sender() {
+        let mut topology = Topology::default();
+        assert!(topology.generate(16, 4, 0, Seed::new(3)));
+        let cache = RouteCache::new(Arc::new(Pathfind));
+
+        for placement in Placement::ALL {
+            assert!(topology.place_faulty(3, placement, 0, Seed::new(3), &cache));
+            let faulty = topology.get_faulty();
+
+            assert_eq!(faulty.len(), 3);
+            assert_eq!(faulty.iter().collect::<HashSet<_>>().len(), 3);
+            assert!(placement == Placement::Random || !faulty.contains(&0));
+        }
+    }
+
+    #[test]
+    fn cut_and_disjoint_paths_witness_connectivity() {
+        // Two triangles sharing node 2, with an extra path 0 - 5 - 4 around it.
+        let edges = vec![
+            (0, 1),
+            (0, 2),
+            (1, 2),
+            (2, 3),
+            (2, 4),
+            (3, 4),
+            (0, 5),
+            (4, 5),
+        ];
+        let flowgraph = FlowGraph::new(&edges);
+
+        assert_eq!(flowgraph.disjoint_paths(1, 3).len(), 2);
+        assert_eq!(flowgraph.min_vertex_cut(0, 1), None);
+
+        let mut cut = flowgraph.min_vertex_cut(1, 3).unwrap();
+        cut.sort_unstable();
+        assert_eq!(cut.len(), 2);
+        assert!(cut.contains(&2));
+
+        for path in flowgraph.disjoint_paths(1, 3) {
+            assert_eq!((path[0], path[path.len() - 1]), (1, 3));
+            assert!(path
+                .windows(2)
+                .all(|w| flowgraph.nodes[&w[0]].contains(&w[1])));
+            assert_eq!(path.iter().filter(|p| cut.contains(p)).count(), 1);
+        }
+
+        let cut = Topology::min_vertex_cut(&edges, 6).unwrap();
+        assert_eq!(cut.len(), Topology::vertex_connectivity(&edges, 6));
+    }
+
+    #[test]
+    fn disjoint_paths_between_neighbours() {
+        // In a triangle, the direct edge and the path over the third node.
+        let flowgraph = FlowGraph::new(&vec![(0, 1), (0, 2), (1, 2)]);
+
+        let mut paths = flowgraph.disjoint_paths(0, 1);
+        assert_eq!(paths.len(), flowgraph.max_vertex_flow(0, 1));
+
+        paths.sort();
+        paths.dedup();
+        assert_eq!(paths, vec![vec![0, 1], vec![0, 2, 1]]);
+    }
+
+    #[test]
+    fn cheapest_disjoint_paths_follow_costs() {
+        // A square 0 - 1 - 2 - 3 with the diagonal 0 - 2.
+        let edges = vec![(0, 1), (1, 2), (2, 3), (0, 3), (0, 2)];
+        let flowgraph = FlowGraph::new(&edges);
+
+        let mut paths = flowgraph.cheapest_disjoint_paths(0, 2, 2, |_, _| 1);
+        paths.sort();
+        assert_eq!(paths, vec![vec![0, 1, 2], vec![0, 2]]);
+
+        // Making the edges of node 1 expensive moves the second path over node 3.
+        let mut paths = flowgraph.cheapest_disjoint_paths(0, 2, 3, |a, b| match a == 1 || b == 1 {
+            true => 10,
+            false => 1,
+        });
+        paths.sort();
+        assert_eq!(paths, vec![vec![0, 1, 2], vec![0, 2], vec![0, 3, 2]]);
+
+        let paths = flowgraph.cheapest_disjoint_paths(0, 2, 2, |a, b| match a == 1 || b == 1 {
+            true => 10,
+            false => 1,
+        });
+        assert_eq!(paths.len(), 2);
+        assert!(paths.contains(&vec![0, 3, 2]));
+    }
+
+    #[tokio::test]
+    async fn latencies_round_trip() {
+        let mut topology = Topology::from_edges(vec![(0, 1), (1, 2), (0, 2)], Vec::new());
+        topology.set_latency(2, 1, Duration::from_millis(120));
+
+        let path = std::env::temp_dir().join("dissyssym-latencies.tpgy");
+        topology.write(&path);
+        let parsed = Topology::parse(&path, 0, Seed::new(0)).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(parsed.get_latency(1, 2), Duration::from_millis(120));
+        assert_eq!(parsed.get_latency(0, 1), Topology::DEFAULT_LATENCY);
+    }
+
+    #[test]
+    fn generation_is_reproducible() {
+        // Small enough that some stubs are left over and shuffled again.
+        let mut topology = Topology::default();
+        assert!(topology.generate(10, 3, 1, Seed::new(42)));
+
+        let expected = vec![
+            (3, 8),
+            (0, 5),
+            (2, 5),
+            (1, 6),
+            (2, 9),
+            (1, 7),
+            (0, 4),
+            (3, 9),
+            (1, 8),
+            (4, 9),
+            (4, 8),
+            (0, 7),
+            (3, 7),
+            (2, 6),
+            (5, 6),
+        ];
+        assert_eq!(topology.get_edges(), expected);
+        assert_eq!(topology.get_faulty(), vec![1]);
+    }
+}
@@ -1,120 +1,244 @@
 use std::{
-    fs::{self, File},
+    fs::File,
     io::Write,
-    path::PathBuf,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 
 use dissyssym_lib::{
     algorithms::{Algorithm, RoutedAlgorithm},
-    Graph, Message, RouteCache, Topology,
+    byzantine::{Delay, Equivocate, Forge, Replay, SelectiveDrop, Silent},
+    strategy, BroadcastId, FlowGraph, Graph, Message, Placement, RouteCache, Seed, Topology,
 };
-use rand::prelude::SliceRandom;
+use rand::prelude::{IteratorRandom, SliceRandom};
 use rayon::prelude::*;
 
+/// The Byzantine behaviours a single placement can be simulated with.
+const BEHAVIORS: [&str; 6] = ["silent", "drop", "forge", "replay", "equivocate", "delay"];
+
 #[tokio::main]
 async fn main() {
-    let mut entries = fs::read_dir("./topologies")
-        .expect("Failed to read topologies dir.")
-        .map(|res| res.unwrap().path())
-        .filter(|p| {
-            let file = p.file_name().unwrap().to_str().unwrap();
-            let mut split = file.split("-");
-            let n = split.next().unwrap().parse::<usize>().unwrap();
-            n <= 20
-        })
-        .collect::<Vec<PathBuf>>();
-    entries.sort();
+    let seed = Seed::from_arg(1);
+    println!("Experiment seed: {}.", seed);
+
+    // By default every faulty set is checked against the route tables, instead
+    // of simulating a single placement with the given behaviour.
+    let behavior = std::env::args()
+        .nth(2)
+        .unwrap_or_else(|| "exhaustive".to_string());
+    if behavior != "exhaustive" && !BEHAVIORS.contains(&behavior.as_str()) {
+        panic!("Invalid faulty behaviour!");
+    }
+    println!("Faulty behaviour: {}.", behavior);
+
+    let placement = std::env::args()
+        .nth(3)
+        .unwrap_or_else(|| "random".to_string());
+    println!("Faulty placement: {}.", placement);
+
+    // The worst case tries every placement, and fails on the first one that breaks.
+    let placements = match placement.as_str() {
+        "worst" => Placement::ALL.to_vec(),
+        name => vec![Placement::from_name(name).expect("Invalid faulty placement!")],
+    };
+
+    let names = std::env::args()
+        .nth(4)
+        .unwrap_or_else(|| "pathfind,unreliable".to_string());
+    let mut strategies = Vec::new();
+    for name in names.split(',') {
+        match strategy::from_name(name) {
+            Some(strategy) => strategies.push(strategy),
+            None => {
+                eprintln!(
+                    "Unknown route strategy `{}`, choose one of: {}.",
+                    name,
+                    strategy::get_names().join(", ")
+                );
+                return;
+            }
+        }
+    }
+    println!("Route strategies: {}.", names);
+
+    let entries = Topology::list("./topologies", 20);
 
-    let cache_p = Arc::new(Mutex::new(RouteCache::new(String::from("pathfind"))));
-    let cache_f = Arc::new(Mutex::new(RouteCache::new(String::from("unreliable"))));
+    let caches = strategies
+        .into_iter()
+        .map(|strategy| Arc::new(RouteCache::new(strategy).with_store("./routes")))
+        .collect::<Vec<_>>();
     let handle = tokio::runtime::Handle::current();
     let _ = handle.enter();
 
     let total = Arc::new(Mutex::new(0));
-    let fails_p = Arc::new(Mutex::new(0));
-    let fails_f = Arc::new(Mutex::new(0));
+    let fails = caches.iter().map(|_| Mutex::new(0)).collect::<Vec<_>>();
     let results = Mutex::new(File::create("./failures.data").unwrap());
 
     entries.par_iter().for_each(|path| {
+        let file = path.file_name().unwrap().to_string_lossy();
+        let i = Topology::file_index(path);
+
         for f in 0.. {
-            let top = match handle.block_on(Topology::parse(path.clone(), f)) {
-                Some(top) => Arc::new(top),
+            let run_seed = seed.derive(&format!("{}-{}", file, f));
+            let top = match handle.block_on(Topology::parse(path.clone(), f, run_seed)) {
+                Some(top) => top,
                 None => break,
             };
 
-            let resr_p = handle.block_on(run_simulation::<RoutedAlgorithm>(
-                top.clone(),
-                cache_p.clone(),
-            ));
-
-            let resr_f = handle.block_on(run_simulation::<RoutedAlgorithm>(
-                top.clone(),
-                cache_f.clone(),
-            ));
+            let nodes = (0..top.get_n()).collect::<Vec<usize>>();
+            let sender = *nodes
+                .choose(&mut run_seed.rng("sender"))
+                .expect("Failed to get random node.");
 
             *total.lock().unwrap() += 1;
 
-            if !resr_p {
-                let result = format!(
-                    "n: {}, f: {}, c: {}, a: p\n",
-                    top.get_n(),
-                    top.get_faulty().len(),
-                    top.get_c()
-                );
-
-                *fails_p.lock().unwrap() += 1;
-                results.lock().unwrap().write(result.as_bytes()).unwrap();
-                print!("{}", result);
-            }
-
-            if !resr_f {
-                let result = format!(
-                    "n: {}, f: {}, c: {}, a: f\n",
-                    top.get_n(),
-                    top.get_faulty().len(),
-                    top.get_c()
-                );
-
-                *fails_f.lock().unwrap() += 1;
-                results.lock().unwrap().write(result.as_bytes()).unwrap();
-                print!("{}", result);
+            for (cache, fails) in caches.iter().zip(&fails) {
+                let a = cache.get_strategy().get_name().to_string();
+                let failed = match behavior.as_str() {
+                    "exhaustive" => verify(&top, f, cache).map(|reason| {
+                        println!("{}-{} (a: {}, f: {}): {}", top.get_n(), i, a, f, reason);
+                        "all"
+                    }),
+                    _ => placements
+                        .iter()
+                        .find(|&&placement| {
+                            let mut placed = top.clone();
+
+                            if !placed.place_faulty(f, placement, sender, run_seed, cache) {
+                                return true;
+                            }
+
+                            !handle.block_on(run_simulation::<RoutedAlgorithm>(
+                                Arc::new(placed),
+                                run_seed,
+                                sender,
+                                &behavior,
+                                cache.clone(),
+                            ))
+                        })
+                        .map(|placement| placement.get_name()),
+                };
+
+                if let Some(placement) = failed {
+                    let result = format!(
+                        "n: {}, f: {}, c: {}, i: {}, s: {}, a: {}, p: {}\n",
+                        top.get_n(),
+                        top.get_faulty().len(),
+                        top.get_c(),
+                        i,
+                        run_seed,
+                        a,
+                        placement,
+                    );
+
+                    *fails.lock().unwrap() += 1;
+                    results
+                        .lock()
+                        .unwrap()
+                        .write_all(result.as_bytes())
+                        .unwrap();
+                    print!("{}", result);
+                }
             }
         }
     });
 
-    println!(
-        "Total: {} | P Fails: {} | F Fails: {}",
-        total.lock().unwrap(),
-        fails_p.lock().unwrap(),
-        fails_f.lock().unwrap(),
-    )
+    let fails = caches
+        .iter()
+        .zip(&fails)
+        .map(|(cache, fails)| {
+            format!(
+                " | {} fails: {}",
+                cache.get_strategy().get_name(),
+                fails.lock().unwrap()
+            )
+        })
+        .collect::<String>();
+    println!("Total: {}{}", total.lock().unwrap(), fails);
 }
 
 async fn run_simulation<T: Algorithm + Send + Sync + 'static>(
     top: Arc<Topology>,
-    cache: Arc<Mutex<RouteCache>>,
+    seed: Seed,
+    sender: usize,
+    behavior: &str,
+    cache: Arc<RouteCache>,
 ) -> bool {
-    let mut graph: Graph<T> = match Graph::new(top.clone(), cache).await {
+    let mut graph: Graph<T> = match Graph::new_simulated(top.clone(), cache, seed).await {
         Some(g) => g,
         None => return false,
     };
 
-    let mut rng = rand::thread_rng();
-    let sender = graph
-        .get_nodes()
-        .choose(&mut rng)
-        .expect("Failed to get random node.")
-        .clone();
+    let mut behavior_rng = seed.rng("behavior");
+    for faulty in top.get_faulty() {
+        match behavior {
+            "silent" => graph.set_behavior(faulty, Silent).await,
+            "drop" => {
+                let neighbours = top.get_neighbours(faulty);
+                let dropped = neighbours
+                    .iter()
+                    .copied()
+                    .choose_multiple(&mut behavior_rng, neighbours.len() / 2);
+                graph
+                    .set_behavior(faulty, SelectiveDrop::new(dropped))
+                    .await
+            }
+            "forge" => {
+                // Claiming the real sender would relay the broadcast unchanged.
+                let claimed = (0..top.get_n())
+                    .filter(|&n| n != sender)
+                    .choose(&mut behavior_rng)
+                    .unwrap();
+                graph.set_behavior(faulty, Forge::new(claimed)).await
+            }
+            "replay" => graph.set_behavior(faulty, Replay::default()).await,
+            "equivocate" => graph.set_behavior(faulty, Equivocate).await,
+            "delay" => {
+                let delay = Delay::new(Duration::from_millis(500));
+                graph.set_behavior(faulty, delay).await
+            }
+            _ => unreachable!("The behaviour is checked up front."),
+        }
+    }
 
-    let sender_id = sender.read().await.get_label();
+    let node = graph.get_nodes()[sender].clone();
     graph
-        .broadcast(sender, Message::new(sender_id, "msg".to_string()))
+        .broadcast(node, Message::new(BroadcastId::new(sender, 0), "msg"))
         .await;
 
     // Wait till finish and collect results.
     graph.wait_settled().await;
     let delivered = graph.get_delivered_broadcasts().await;
+    let forged = graph.get_forged_deliveries().await;
+
+    delivered > 99.95 && forged == 0
+}
+
+/// Checks the routes of every sender against every set of `f` crashed nodes,
+/// describing the first set that keeps a correct node from delivering.
+fn verify(top: &Topology, f: usize, cache: &RouteCache) -> Option<String> {
+    let nodes = FlowGraph::new(&top.get_edges()).get_nodes();
+
+    for s in 0..top.get_n() {
+        let routes = match cache.gen_routes(&nodes, f, s) {
+            Some(routes) => routes,
+            None => return Some(format!("no routes for sender {}", s)),
+        };
+
+        // Enough disjoint paths prove the routes correct without trying every set.
+        if RouteCache::verify_routes(&routes, f, s).is_empty() {
+            continue;
+        }
+
+        if let Some(counterexample) = RouteCache::find_counterexample(&nodes, &routes, f, s) {
+            return Some(format!(
+                "sender {} with faulty {:?} leaves {:?} undelivered",
+                s,
+                counterexample.get_faulty(),
+                counterexample.get_undelivered()
+            ));
+        }
+    }
 
-    delivered > 99.95
+    None
 }
@@ -1,15 +1,18 @@
 use std::path::Path;
 
-use dissyssym_lib::Topology;
+use dissyssym_lib::{Seed, Topology};
 use rayon::prelude::*;
 
 fn main() {
+    let seed = Seed::from_arg(1);
+    println!("Experiment seed: {}.", seed);
+
     (2..100)
         .into_par_iter()
-        .for_each(|n| (1..n).into_par_iter().for_each(|c| gen_n_c(n, c)));
+        .for_each(|n| (1..n).into_par_iter().for_each(|c| gen_n_c(n, c, seed)));
 }
 
-fn gen_n_c(n: usize, c: usize) {
+fn gen_n_c(n: usize, c: usize, seed: Seed) {
     if n * c % 2 == 1 {
         return;
     }
@@ -28,7 +31,7 @@ fn gen_n_c(n: usize, c: usize) {
         );
 
         let mut topology = Topology::default();
-        if !topology.generate(n, c, 0) {
+        if !topology.generate(n, c, 0, seed.derive(&format!("{}-{}-{}", n, c, i))) {
             println!("Failed to generate topology, going to the next connectivity.");
             break;
         }
@@ -0,0 +1,208 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::Write,
+    sync::Arc,
+    time::Duration,
+};
+
+use dissyssym_lib::{
+    algorithms::{Algorithm, FloodingAlgorithm, RoutedAlgorithm},
+    strategy::{self, Latency, Optimal, RouteStrategy, Suurballe},
+    BroadcastId, FlowGraph, Graph, Load, Message, RouteCache, Seed, Topology,
+};
+
+/// The trade-offs the latency-aware strategy is swept over, in mean edge latencies
+/// per extra route edge.
+const TRADEOFFS: [f64; 7] = [0., 0.25, 0.5, 1., 2., 4., 8.];
+
+/// The costs of a routed and of a new edge Suurballe's strategy is swept over, from
+/// sharing at any length to not preferring shared edges at all. The default of two
+/// thirds is measured as `suurballe` already.
+const REUSE_COSTS: [(u64, u64); 5] = [(0, 1), (1, 3), (1, 2), (4, 5), (1, 1)];
+
+#[tokio::main]
+async fn main() {
+    let path = std::env::args()
+        .nth(1)
+        .expect("Usage: pareto <topology> [f] [seed]");
+    let f = match std::env::args().nth(2) {
+        Some(f) => f.parse::<usize>().expect("Failed to parse f."),
+        None => 1,
+    };
+    let seed = Seed::from_arg(3);
+    println!("Experiment seed: {}.", seed);
+
+    let top = match Topology::parse(&path, f, seed).await {
+        Some(top) => Arc::new(top),
+        None => {
+            eprintln!("The topology cannot tolerate {} faulty nodes.", f);
+            return;
+        }
+    };
+
+    let mut strategies = strategy::all()
+        .into_iter()
+        .filter(|s| s.get_name() != "optimal" || top.get_n() <= Optimal::MAX_NODES)
+        .map(|s| (s.get_name().to_string(), s))
+        .collect::<Vec<(String, Arc<dyn RouteStrategy>)>>();
+    for (shared, new) in REUSE_COSTS {
+        strategies.push((
+            format!("suurballe-{}-{}", shared, new),
+            Arc::new(Suurballe::new(shared, new)),
+        ));
+    }
+    for tradeoff in TRADEOFFS {
+        strategies.push((
+            format!("latency-{}", tradeoff),
+            Arc::new(Latency::new(&top, tradeoff)),
+        ));
+    }
+
+    // Flooding needs no routes, so any cache will do.
+    let mut points = Vec::new();
+    let cache = Arc::new(RouteCache::new(strategies[0].1.clone()));
+    if let Some(point) = measure::<FloodingAlgorithm>("flooding", top.clone(), cache, seed).await {
+        points.push(point);
+    }
+
+    // Strategies that build the same routes as one measured already would only
+    // print its row again, as when no edge has a latency of its own.
+    let nodes = FlowGraph::new(&top.get_edges()).get_nodes();
+    let mut measured = Vec::<(String, HashMap<usize, Arc<HashMap<usize, HashSet<usize>>>>)>::new();
+    for (name, strategy) in strategies {
+        let cache = Arc::new(RouteCache::new(strategy));
+
+        if let Some(routes) = cache.gen_all_routes(&nodes, f) {
+            if let Some((same, _)) = measured.iter().find(|(_, other)| *other == routes) {
+                println!("{} builds the same routes as {}.", name, same);
+                continue;
+            }
+
+            measured.push((name.clone(), routes));
+        }
+
+        if let Some(point) = measure::<RoutedAlgorithm>(&name, top.clone(), cache, seed).await {
+            points.push(point);
+        }
+    }
+
+    let mut results = File::create("./pareto.data").unwrap();
+
+    for point in &points {
+        let result = format!(
+            "[n: {}, f: {}, c: {}, s: {}] a: {}, d: {:.1}%, m: {:.1}, mean: {:.1}, max: {}, load: max {:.1}, mean {:.1}, j {:.3}, p: {}\n",
+            top.get_n(),
+            f,
+            top.get_c(),
+            seed,
+            point.name,
+            point.delivered,
+            point.messages,
+            point.mean.as_secs_f64() * 1000.,
+            point.max.as_millis(),
+            point.load.get_max() as f64 / top.get_n() as f64,
+            point.load.get_mean() / top.get_n() as f64,
+            point.load.get_fairness(),
+            point.on_frontier(&points),
+        );
+
+        results.write_all(result.as_bytes()).unwrap();
+        print!("{}", result);
+    }
+
+    let mut frontier = points
+        .iter()
+        .filter(|point| point.on_frontier(&points))
+        .collect::<Vec<&Point>>();
+    frontier.sort_by(|a, b| a.messages.total_cmp(&b.messages));
+
+    println!("Pareto frontier:");
+    for point in frontier {
+        println!(
+            "  {}: {:.1} messages, {:.1} ms mean, {} ms max",
+            point.name,
+            point.messages,
+            point.mean.as_secs_f64() * 1000.,
+            point.max.as_millis()
+        );
+    }
+}
+
+/// Broadcasts once from every node, and averages the share of correct nodes that
+/// delivered, the messages sent per broadcast and the time it took correct nodes to
+/// deliver. The load of every node is summed
+/// over all broadcasts.
+async fn measure<T: Algorithm + Send + Sync + 'static>(
+    name: &str,
+    top: Arc<Topology>,
+    cache: Arc<RouteCache>,
+    seed: Seed,
+) -> Option<Point> {
+    let mut delivered = 0.;
+    let mut messages = 0;
+    let mut times = Vec::new();
+    let mut load = Load::default();
+
+    for sender in 0..top.get_n() {
+        let run_seed = seed.derive(&sender.to_string());
+        let mut graph: Graph<T> =
+            Graph::new_simulated(top.clone(), cache.clone(), run_seed).await?;
+
+        let node = graph.get_nodes()[sender].clone();
+        graph
+            .broadcast(node, Message::new(BroadcastId::new(sender, 0), "msg"))
+            .await;
+        graph.wait_settled().await;
+
+        delivered += graph.get_delivered_broadcasts().await;
+        messages += graph.get_total_messages().await;
+        times.extend(graph.get_delivery_times());
+        load.add(&graph.get_load().await);
+    }
+
+    Some(Point {
+        name: name.to_string(),
+        delivered: delivered / top.get_n() as f64,
+        messages: messages as f64 / top.get_n() as f64,
+        mean: times.iter().sum::<Duration>() / times.len().max(1) as u32,
+        max: times.iter().max().copied().unwrap_or_default(),
+        load,
+    })
+}
+
+/// The cost of one configuration, per broadcast.
+struct Point {
+    name: String,
+    delivered: f64,
+    messages: f64,
+    mean: Duration,
+    max: Duration,
+    load: Load,
+}
+
+impl Point {
+    /// Whether every correct node delivered every broadcast. Latencies only count
+    /// the nodes that delivered, so a configuration that loses deliveries would
+    /// look faster than it is.
+    fn is_reliable(&self) -> bool {
+        self.delivered > 99.95
+    }
+
+    /// Whether this configuration is reliable and no other reliable one dominates it.
+    fn on_frontier(&self, points: &[Point]) -> bool {
+        self.is_reliable()
+            && !points
+                .iter()
+                .any(|other| other.is_reliable() && other.dominates(self))
+    }
+
+    /// Whether this configuration is no worse than `other` on every count, and
+    /// better on at least one.
+    fn dominates(&self, other: &Point) -> bool {
+        let this = (self.messages, self.mean, self.max);
+        let that = (other.messages, other.mean, other.max);
+
+        this.0 <= that.0 && this.1 <= that.1 && this.2 <= that.2 && this != that
+    }
+}
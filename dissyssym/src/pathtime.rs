@@ -1,27 +1,31 @@
-use std::{
-    fs::{self, File},
-    io::Write,
-    path::PathBuf,
-    sync::Mutex,
-};
+use std::{fs::File, io::Write, sync::Mutex};
 
-use dissyssym_lib::{FlowGraph, RouteCache, Topology};
+use dissyssym_lib::{strategy, FlowGraph, RouteCache, Seed, Topology};
 use rayon::prelude::*;
 use tokio::time::Instant;
 
 #[tokio::main]
 async fn main() {
-    let mut entries = fs::read_dir("./topologies")
-        .expect("Failed to read topologies dir.")
-        .map(|res| res.unwrap().path())
-        .filter(|p| {
-            let file = p.file_name().unwrap().to_str().unwrap();
-            let mut split = file.split("-");
-            let n = split.next().unwrap().parse::<usize>().unwrap();
-            n <= 30
-        })
-        .collect::<Vec<PathBuf>>();
-    entries.sort();
+    let names = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "pathfind,unreliable,mincost".to_string());
+    let mut strategies = Vec::new();
+    for name in names.split(',') {
+        match strategy::from_name(name) {
+            Some(strategy) => strategies.push(strategy),
+            None => {
+                eprintln!(
+                    "Unknown route strategy `{}`, choose one of: {}.",
+                    name,
+                    strategy::get_names().join(", ")
+                );
+                return;
+            }
+        }
+    }
+    println!("Route strategies: {}.", names);
+
+    let entries = Topology::list("./topologies", 30);
 
     let handle = tokio::runtime::Handle::current();
     let _ = handle.enter();
@@ -30,36 +34,39 @@ async fn main() {
 
     entries.par_iter().for_each(|path| {
         for f in 0.. {
-            let top = match handle.block_on(Topology::parse(path.clone(), f)) {
+            // The faulty set does not influence route generation, so any seed will do.
+            let top = match handle.block_on(Topology::parse(path.clone(), f, Seed::new(0))) {
                 Some(top) => top,
                 None => break,
             };
             let nodes = FlowGraph::new(&top.get_edges()).get_nodes();
 
-            let cache_path = RouteCache::new(String::from("pathfind"));
-            let start_path = Instant::now();
-            for (&i, _) in &nodes {
-                cache_path.gen_routes_uncached(&nodes, f, i);
-            }
-            let timings_path = start_path.elapsed().as_millis();
+            let timings = strategies
+                .iter()
+                .map(|strategy| {
+                    let cache = RouteCache::new(strategy.clone());
+                    let start = Instant::now();
+                    for &i in nodes.keys() {
+                        cache.gen_routes_uncached(&nodes, f, i);
+                    }
 
-            let cache_fast = RouteCache::new(String::from("unreliable"));
-            let start_fast = Instant::now();
-            for (&i, _) in &nodes {
-                cache_fast.gen_routes_uncached(&nodes, f, i);
-            }
-            let timings_fast = start_fast.elapsed().as_millis();
+                    format!("{} {}", strategy.get_name(), start.elapsed().as_millis())
+                })
+                .collect::<Vec<String>>();
 
             let result = format!(
-                "[n: {}, f: {}, c: {}] p {} | f {}\n",
+                "[n: {}, f: {}, c: {}] {}\n",
                 top.get_n(),
                 f,
                 top.get_c(),
-                timings_path,
-                timings_fast
+                timings.join(" | ")
             );
 
-            results.lock().unwrap().write(result.as_bytes()).unwrap();
+            results
+                .lock()
+                .unwrap()
+                .write_all(result.as_bytes())
+                .unwrap();
             print!("{}", result);
         }
     });
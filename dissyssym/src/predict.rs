@@ -0,0 +1,97 @@
+use std::{
+    fs::File,
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
+use dissyssym_lib::{predict_flooding, predict_messages, strategy, Prediction, Seed, Topology};
+use rand::prelude::SliceRandom;
+use rayon::prelude::*;
+
+/// Predicts the broadcasts of `simulate` without running them, with the same seeds
+/// and senders, so every row can be checked against the row `simulate` gives.
+#[tokio::main]
+async fn main() {
+    let seed = Seed::from_arg(1);
+    println!("Experiment seed: {}.", seed);
+
+    let name = std::env::args()
+        .nth(2)
+        .unwrap_or_else(|| "pathfind".to_string());
+    let strategy = match strategy::from_name(&name) {
+        Some(strategy) => strategy,
+        None => {
+            eprintln!(
+                "Unknown route strategy `{}`, choose one of: {}.",
+                name,
+                strategy::get_names().join(", ")
+            );
+            return;
+        }
+    };
+    println!("Route strategy: {}.", strategy.get_name());
+
+    let entries = Topology::list("./topologies", 20);
+
+    let handle = tokio::runtime::Handle::current();
+    let _ = handle.enter();
+
+    let results = Mutex::new(File::create("./predict.data").unwrap());
+
+    entries.par_iter().for_each(|path| {
+        let file = path.file_name().unwrap().to_string_lossy();
+        let i = Topology::file_index(path);
+
+        for f in 0.. {
+            let run_seed = seed.derive(&format!("{}-{}", file, f));
+            let top = match handle.block_on(Topology::parse(path.clone(), f, run_seed)) {
+                Some(top) => Arc::new(top),
+                None => break,
+            };
+
+            // The same sender `simulate` picks from the nodes of its graph.
+            let nodes = (0..top.get_n()).collect::<Vec<usize>>();
+            let sender = *nodes
+                .choose(&mut run_seed.rng("sender"))
+                .expect("Failed to get random node.");
+
+            let flooding = predict_flooding(&top, sender);
+            let routed = match predict_messages(&top, strategy.as_ref(), f, sender) {
+                Some(routed) => routed,
+                None => continue,
+            };
+
+            let result = format!(
+                "[n: {}, f: {}, c: {}, i: {}, s: {}] f: d {}%, m {} | r: d {}%, m {}\n",
+                top.get_n(),
+                top.get_faulty().len(),
+                top.get_c(),
+                i,
+                run_seed,
+                delivered(&top, &flooding),
+                flooding.get_total(),
+                delivered(&top, &routed),
+                routed.get_total(),
+            );
+
+            results
+                .lock()
+                .unwrap()
+                .write_all(result.as_bytes())
+                .unwrap();
+            print!("{}", result);
+        }
+    });
+}
+
+/// The percentage of correct nodes that deliver, as `simulate` reports it.
+fn delivered(top: &Topology, prediction: &Prediction) -> f64 {
+    let faulty = top.get_faulty();
+    let correct = prediction
+        .get_delivered()
+        .into_iter()
+        .filter(|n| !faulty.contains(n))
+        .count();
+
+    (correct as f64 / (top.get_n() - faulty.len()) as f64) * 100.
+}
@@ -1,54 +1,89 @@
 use std::{
-    fs::{self, File},
+    fs::File,
     io::Write,
-    path::PathBuf,
     sync::{Arc, Mutex},
     time::Duration,
 };
 
 use dissyssym_lib::{
-    algorithms::{Algorithm, FloodingAlgorithm, RoutedAlgorithm},
-    Graph, Message, RouteCache, Topology,
+    algorithms::{
+        Algorithm, BrachaDolevAlgorithm, DolevAlgorithm, DolevOptimised, FloodingAlgorithm,
+        Optimised, RoutedAlgorithm, RoutedDolevAlgorithm,
+    },
+    strategy::{self, Optimal},
+    BroadcastId, Graph, Load, Message, RouteCache, Seed, Topology,
 };
 use rand::prelude::SliceRandom;
 use rayon::prelude::*;
-use tokio::time::Instant;
+
+/// The memory the route tables of a strategy may take, as the sweep covers far
+/// more topologies than need their routes at the same time.
+const ROUTE_BUDGET: usize = 512 * 1024 * 1024;
+
+/// The largest topologies Dolev is simulated on, as it relays a copy along every
+/// simple path and the number of those grows exponentially with the degree.
+const DOLEV_MAX_NODES: usize = 8;
 
 #[tokio::main]
 async fn main() {
-    let mut entries = fs::read_dir("./topologies")
-        .expect("Failed to read topologies dir.")
-        .map(|res| res.unwrap().path())
-        .filter(|p| {
-            let file = p.file_name().unwrap().to_str().unwrap();
-            let mut split = file.split("-");
-            let n = split.next().unwrap().parse::<usize>().unwrap();
-            n <= 20
-        })
-        .collect::<Vec<PathBuf>>();
-    entries.sort();
-
-    let cache = Arc::new(Mutex::new(RouteCache::new(String::from("pathfind"))));
+    let seed = Seed::from_arg(1);
+    println!("Experiment seed: {}.", seed);
+
+    let name = std::env::args()
+        .nth(2)
+        .unwrap_or_else(|| "pathfind".to_string());
+    let strategy = match strategy::from_name(&name) {
+        Some(strategy) => strategy,
+        None => {
+            eprintln!(
+                "Unknown route strategy `{}`, choose one of: {}.",
+                name,
+                strategy::get_names().join(", ")
+            );
+            return;
+        }
+    };
+    println!("Route strategy: {}.", strategy.get_name());
+
+    let entries = Topology::list("./topologies", 20);
+
+    let cache = Arc::new(
+        RouteCache::new(strategy)
+            .with_store("./routes")
+            .with_budget(ROUTE_BUDGET),
+    );
+    let optimal_cache = Arc::new(
+        RouteCache::new(Arc::new(Optimal))
+            .with_store("./routes")
+            .with_budget(ROUTE_BUDGET),
+    );
     let handle = tokio::runtime::Handle::current();
     let _ = handle.enter();
 
     let totalf = Mutex::new(0);
     let totalr = Mutex::new(0);
     let results = Mutex::new(File::create("./results.data").unwrap());
+    let bracha_results = Mutex::new(File::create("./results.bracha.data").unwrap());
 
     entries.par_iter().for_each(|path| {
+        let file = path.file_name().unwrap().to_string_lossy();
+        let i = Topology::file_index(path);
+
         for f in 0.. {
-            let top = match handle.block_on(Topology::parse(path.clone(), f)) {
+            let run_seed = seed.derive(&format!("{}-{}", file, f));
+            let top = match handle.block_on(Topology::parse(path.clone(), f, run_seed)) {
                 Some(top) => Arc::new(top),
                 None => break,
             };
 
             let resf = handle.block_on(run_simulation::<FloodingAlgorithm>(
                 top.clone(),
+                run_seed,
                 cache.clone(),
             ));
             let resr = handle.block_on(run_simulation::<RoutedAlgorithm>(
                 top.clone(),
+                run_seed,
                 cache.clone(),
             ));
 
@@ -65,25 +100,124 @@ async fn main() {
                 panic!("oh no, delivery is bad again :(");
             }
 
+            // The exact routes are only feasible to find for small topologies.
+            let reso = match top.get_n() <= Optimal::MAX_NODES {
+                true => handle.block_on(run_simulation::<RoutedAlgorithm>(
+                    top.clone(),
+                    run_seed,
+                    optimal_cache.clone(),
+                )),
+                false => None,
+            };
+            let optimal = match reso {
+                Some(reso) => format!(
+                    " | o: d {}%, m {}, t: {}",
+                    reso.delivered,
+                    reso.messages,
+                    reso.duration.as_millis()
+                ),
+                None => String::new(),
+            };
+
+            let dolev = match top.get_n() <= DOLEV_MAX_NODES {
+                true => handle
+                    .block_on(run_simulation::<DolevAlgorithm>(
+                        top.clone(),
+                        run_seed,
+                        cache.clone(),
+                    ))
+                    .zip(handle.block_on(run_simulation::<RoutedDolevAlgorithm>(
+                        top.clone(),
+                        run_seed,
+                        cache.clone(),
+                    ))),
+                false => None,
+            };
+            let dolev = match dolev {
+                Some((resdf, resdr)) => format!(
+                    " | df: d {}%, m {}, t: {} | dr: d {}%, m {}, t: {}",
+                    resdf.delivered,
+                    resdf.messages,
+                    resdf.duration.as_millis(),
+                    resdr.delivered,
+                    resdr.messages,
+                    resdr.duration.as_millis()
+                ),
+                None => String::new(),
+            };
+
             let result = format!(
-                "[n: {}, f: {}, c: {}, i: {}] f: d {}%, m {}, t: {} | r: d {}%, m {}, t: {}\n",
+                "[n: {}, f: {}, c: {}, i: {}, s: {}] f: d {}%, m {}, t: {} | r: d {}%, m {}, t: {} | load: max {}, mean {:.1}, j {:.3}{}{}\n",
                 top.get_n(),
                 top.get_faulty().len(),
                 top.get_c(),
-                0,
+                i,
+                run_seed,
                 resf.delivered,
                 resf.messages,
                 resf.duration.as_millis(),
                 resr.delivered,
                 resr.messages,
-                resr.duration.as_millis()
+                resr.duration.as_millis(),
+                resr.load.get_max(),
+                resr.load.get_mean(),
+                resr.load.get_fairness(),
+                dolev,
+                optimal
             );
 
-            results.lock().unwrap().write(result.as_bytes()).unwrap();
+            results
+                .lock()
+                .unwrap()
+                .write_all(result.as_bytes())
+                .unwrap();
             print!("{}", result);
 
             *totalf.lock().unwrap() += resf.messages;
             *totalr.lock().unwrap() += resr.messages;
+
+            // Bracha-Dolev only works with n > 3f on a 2f + 1 vertex connected network.
+            if top.get_n() <= 3 * f || top.get_c() <= 2 * f {
+                continue;
+            }
+
+            let resmd = handle.block_on(run_simulation::<BrachaDolevAlgorithm<DolevOptimised>>(
+                top.clone(),
+                run_seed,
+                cache.clone(),
+            ));
+            let resmbd = handle.block_on(run_simulation::<BrachaDolevAlgorithm<Optimised>>(
+                top.clone(),
+                run_seed,
+                cache.clone(),
+            ));
+
+            let (resmd, resmbd) = match (resmd, resmbd) {
+                (Some(md), Some(mbd)) => (md, mbd),
+                _ => continue,
+            };
+
+            let result = format!(
+                "[n: {}, f: {}, c: {}, i: {}, s: {}] md: d {}%, m {}, t: {} | mbd: d {}%, m {}, t: {}\n",
+                top.get_n(),
+                top.get_faulty().len(),
+                top.get_c(),
+                i,
+                run_seed,
+                resmd.delivered,
+                resmd.messages,
+                resmd.duration.as_millis(),
+                resmbd.delivered,
+                resmbd.messages,
+                resmbd.duration.as_millis()
+            );
+
+            bracha_results
+                .lock()
+                .unwrap()
+                .write_all(result.as_bytes())
+                .unwrap();
+            print!("{}", result);
         }
     });
 
@@ -92,19 +226,17 @@ async fn main() {
         totalf.lock().unwrap(),
         totalr.lock().unwrap()
     );
+    println!("Route cache: {}.", cache.get_stats());
 }
 
 async fn run_simulation<T: Algorithm + Send + Sync + 'static>(
     top: Arc<Topology>,
-    cache: Arc<Mutex<RouteCache>>,
+    seed: Seed,
+    cache: Arc<RouteCache>,
 ) -> Option<SimResult> {
-    let mut graph: Graph<T> = match Graph::new(top.clone(), cache).await {
-        Some(g) => g,
-        None => return None,
-    };
+    let mut graph: Graph<T> = Graph::new_simulated(top.clone(), cache, seed).await?;
 
-    let now = Instant::now();
-    let mut rng = rand::thread_rng();
+    let mut rng = seed.rng("sender");
 
     let sender = graph
         .get_nodes()
@@ -114,22 +246,21 @@ async fn run_simulation<T: Algorithm + Send + Sync + 'static>(
 
     let sender_id = sender.read().await.get_label();
     graph
-        .broadcast(sender, Message::new(sender_id, "msg".to_string()))
+        .broadcast(sender, Message::new(BroadcastId::new(sender_id, 0), "msg"))
         .await;
 
     // Wait till finish and collect results.
     graph.wait_settled().await;
-    let duration = now.elapsed();
+    let duration = graph.get_virtual_time().unwrap_or_default();
     let messages = graph.get_total_messages().await;
     let delivered = graph.get_delivered_broadcasts().await;
+    let load = graph.get_load().await;
 
     Some(SimResult {
-        c: top.get_c(),
-        f: top.get_faulty().len(),
-        n: top.get_n(),
         messages: messages as usize,
         delivered,
         duration,
+        load,
     })
 }
 
@@ -137,7 +268,5 @@ struct SimResult {
     delivered: f64,
     messages: usize,
     duration: Duration,
-    c: usize,
-    f: usize,
-    n: usize,
+    load: Load,
 }
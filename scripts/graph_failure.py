@@ -2,29 +2,37 @@ from parse import parse
 import matplotlib.pyplot as plt
 import sys
 
-TEMPLATE = "n: {:d}, f: {:d}, c: {:d}, a: {}"
+TEMPLATE = "n: {:d}, f: {:d}, c: {:d}, i: {:d}, s: {:d}, a: {}, p: {}"
+# Results written before the faulty placement was recorded.
+SEED_TEMPLATE = "n: {:d}, f: {:d}, c: {:d}, i: {:d}, s: {:d}, a: {}"
+# Results written before the seed was recorded.
+LEGACY_TEMPLATE = "n: {:d}, f: {:d}, c: {:d}, a: {}"
+# Results written before the strategies were named, by their initials.
+LEGACY_NAMES = {"p": "pathfind", "f": "unreliable"}
 
 f = open(sys.argv[1], "r")
 lines = f.readlines()
 
-fast_x = []
-fast_y = []
-path_x = []
-path_y = []
+failures = {}
 
 for line in lines:
-    parsed = parse(TEMPLATE, line.replace("\n", ""))
-    (n, f, c, a) = parsed
-
-    if a == "f":
-        fast_x.append(n)
-        fast_y.append(c)
+    line = line.replace("\n", "")
+    parsed = parse(TEMPLATE, line)
+    if parsed is not None:
+        (n, f, c, i, s, a, p) = parsed
+    elif parse(SEED_TEMPLATE, line) is not None:
+        (n, f, c, i, s, a) = parse(SEED_TEMPLATE, line)
     else:
-        path_x.append(n)
-        path_y.append(c)
+        (n, f, c, a) = parse(LEGACY_TEMPLATE, line)
+
+    a = LEGACY_NAMES.get(a, a)
+    if a not in failures:
+        failures[a] = ([], [])
+    failures[a][0].append(n)
+    failures[a][1].append(c)
 
-plt.scatter(fast_x, fast_y, label="Fast Algorithm")
-plt.scatter(path_x, path_y, label="Pathfind Algorithm")
+for a, (x, y) in failures.items():
+    plt.scatter(x, y, label=a)
 plt.xlabel("Amount of Nodes (n)")
 plt.ylabel("Faulty Nodes (f)")
 plt.xticks([1, 4, 8, 12, 16, 20])
@@ -2,17 +2,42 @@ from parse import parse
 import matplotlib.pyplot as plt
 import sys
 
-TEMPLATE = "[n: {:d}, f: {:d}, c: {:d}, i: {:d}] f: d {:d}%, m {:d}, t: {:d} | r: d {:d}%, m {:d}, t: {:d}"
+TEMPLATE = "[n: {:d}, f: {:d}, c: {:d}, i: {:d}, s: {:d}] f: d {:d}%, m {:d}, t: {:d} | r: d {:d}%, m {:d}, t: {:d}"
+# Rows of small topologies, which also hold the results of the optimal routes.
+OPTIMAL_TEMPLATE = TEMPLATE + " | o: d {:d}%, m {:d}, t: {:d}"
+# Results written before the seed was recorded.
+LEGACY_TEMPLATE = "[n: {:d}, f: {:d}, c: {:d}, i: {:d}] f: d {:d}%, m {:d}, t: {:d} | r: d {:d}%, m {:d}, t: {:d}"
 
 f = open(sys.argv[1], "r")
 lines = f.readlines()
 
 flood_results = {}
 routed_results = {}
+optimal_results = {}
 
 for line in lines:
-    parsed = parse(TEMPLATE, line.replace("\n", ""))
-    (n, f, c, i, fd, fm, ft, rd, rm, rt) = parsed
+    # The routed load and Dolev's columns, on the smallest topologies, are left
+    # out of this graph.
+    segments = line.replace("\n", "").split(" | ")
+    line = " | ".join(seg for seg in segments if not seg.startswith(("load: ", "df: ", "dr: ")))
+
+    parsed = parse(OPTIMAL_TEMPLATE, line.replace("\n", ""))
+    if parsed is not None:
+        (n, f, c, i, s, fd, fm, ft, rd, rm, rt, od, om, ot) = parsed
+
+        if f not in optimal_results:
+            optimal_results[f] = {}
+        if n not in optimal_results[f]:
+            optimal_results[f][n] = []
+        optimal_results[f][n].append(om)
+
+    parsed = parse(TEMPLATE, line.replace("\n", "").split(" | o: ")[0])
+
+    if parsed is None:
+        parsed = parse(LEGACY_TEMPLATE, line.replace("\n", ""))
+        (n, f, c, i, fd, fm, ft, rd, rm, rt) = parsed
+    else:
+        (n, f, c, i, s, fd, fm, ft, rd, rm, rt) = parsed
 
     if f not in flood_results:
         flood_results[f] = {}
@@ -52,6 +77,18 @@ for f in routed_results:
 
     axs[1].plot(x, y, label=str(routed_results[f]))
 
+for f in optimal_results:
+    x = []
+    y = []
+
+    for n in sorted(optimal_results[f]):
+        m_list = optimal_results[f][n]
+
+        x.append(n)
+        y.append(round(sum(m_list) / len(m_list)))
+
+    axs[1].plot(x, y, linestyle="--", label=str(optimal_results[f]))
+
 axs[0].set_xticks([1, 4, 8, 12, 16, 20])
 axs[0].set_yticks([1, 20, 40, 60, 80, 100, 120, 140, 160])
 axs[0].set_title("Flooding")
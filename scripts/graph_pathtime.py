@@ -2,64 +2,49 @@ from parse import parse
 import matplotlib.pyplot as plt
 import sys
 
-TEMPLATE = "[n: {:d}, f: {:d}, c: {:d}] p {:d} | f {:d}"
+TEMPLATE = "[n: {:d}, f: {:d}, c: {:d}] {}"
+# Results written before the strategies were named, by their initials.
+LEGACY_NAMES = {"p": "pathfind", "f": "unreliable", "m": "mincost"}
 
 f = open(sys.argv[1], "r")
 lines = f.readlines()
 
-fast_results = {}
-path_results = {}
+results = {}
 
 for line in lines:
-    parsed = parse(TEMPLATE, line.replace("\n", ""))
-    (n, f, c, pr, fr) = parsed
+    (n, f, c, timings) = parse(TEMPLATE, line.replace("\n", ""))
 
-    if f not in fast_results:
-        fast_results[f] = {}
-    if n not in fast_results[f]:
-        fast_results[f][n] = []
-    fast_results[f][n].append(fr)
+    for timing in timings.split(" | "):
+        (name, t) = timing.rsplit(" ", 1)
+        name = LEGACY_NAMES.get(name, name)
 
-    if f not in path_results:
-        path_results[f] = {}
-    if n not in path_results[f]:
-        path_results[f][n] = []
-    path_results[f][n].append(pr)
+        if name not in results:
+            results[name] = {}
+        if f not in results[name]:
+            results[name][f] = {}
+        if n not in results[name][f]:
+            results[name][f][n] = []
+        results[name][f][n].append(int(t))
 
-fig, axs = plt.subplots(2, 1, frameon=False, sharey=True)
+fig, axs = plt.subplots(len(results), 1, frameon=False, sharey=True, squeeze=False)
 
-for f in fast_results:
-    x = []
-    y = []
+for ax, name in zip(axs[:, 0], results):
+    for f in results[name]:
+        x = []
+        y = []
 
-    for n in sorted(fast_results[f]):
-        t_list = fast_results[f][n]
+        for n in sorted(results[name][f]):
+            t_list = results[name][f][n]
 
-        x.append(n)
-        y.append(round(sum(t_list) / len(t_list)))
+            x.append(n)
+            y.append(round(sum(t_list) / len(t_list)))
 
-    axs[0].plot(x, y, label=str(fast_results[f]))
+        ax.plot(x, y, label=str(f))
 
-for f in path_results:
-    x = []
-    y = []
-
-    for n in sorted(path_results[f]):
-        t_list = path_results[f][n]
-
-        x.append(n)
-        y.append(round(sum(t_list) / len(t_list)))
-
-    axs[1].plot(x, y, label=str(path_results[f]))
-
-axs[0].set_title("Fast Algorithm")
-axs[0].set_xlabel('Node count (n)')
-axs[0].set_ylabel('Latency (ms)')
-axs[0].set_yscale('log')
-axs[1].set_title("Pathfind Algorithm")
-axs[1].set_xlabel('Node count (n)')
-axs[1].set_ylabel('Latency (ms)')
-axs[1].set_yscale('log')
+    ax.set_title(name)
+    ax.set_xlabel('Node count (n)')
+    ax.set_ylabel('Latency (ms)')
+    ax.set_yscale('log')
 fig.tight_layout()
 
 plt.savefig(sys.argv[1] + ".png", bbox_inches='tight', transparent=True)